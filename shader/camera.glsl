@@ -0,0 +1,35 @@
+#ifndef CAMERA_GLSL
+#define CAMERA_GLSL
+
+#include "ray.glsl"
+
+struct Camera {
+    vec3 pos;
+    vec3 rot;
+    vec2 sensor_size;
+    float focal_distance;
+};
+
+// Euler angles in radians, applied as yaw (y), then pitch (x), then roll (z).
+mat3 camera_rotation(Camera camera) {
+    vec3 c = cos(camera.rot);
+    vec3 s = sin(camera.rot);
+    mat3 rot_x = mat3(1.0, 0.0, 0.0, 0.0, c.x, s.x, 0.0, -s.x, c.x);
+    mat3 rot_y = mat3(c.y, 0.0, -s.y, 0.0, 1.0, 0.0, s.y, 0.0, c.y);
+    mat3 rot_z = mat3(c.z, s.z, 0.0, -s.z, c.z, 0.0, 0.0, 0.0, 1.0);
+    return rot_y * rot_x * rot_z;
+}
+
+// The camera looks down -z with y up. `pixel_pos` is measured in pixels from the top left corner
+// of the image, so pixel centers lie at half-integer positions.
+Ray camera_ray_at(Camera camera, vec2 pixel_pos, uvec2 image_size) {
+    vec2 uv = pixel_pos / vec2(image_size) - 0.5;
+    vec3 sensor_pos = vec3(uv.x * camera.sensor_size.x, -uv.y * camera.sensor_size.y, -camera.focal_distance);
+    return Ray(camera.pos, normalize(camera_rotation(camera) * sensor_pos));
+}
+
+Ray camera_ray(Camera camera, uvec2 pixel, uvec2 image_size) {
+    return camera_ray_at(camera, vec2(pixel) + 0.5, image_size);
+}
+
+#endif
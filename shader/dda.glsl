@@ -0,0 +1,71 @@
+#ifndef DDA_GLSL
+#define DDA_GLSL
+
+#include "scene.glsl"
+
+uint voxel(ivec3 cell) {
+    uvec3 pos = uvec3(cell);
+    return scene_data[pos.z * scene.size.x * scene.size.y + pos.y * scene.size.x + pos.x];
+}
+
+// 3D-DDA traversal (Amanatides & Woo, "A Fast Voxel Traversal Algorithm for Ray Tracing").
+bool trace_scene(Ray ray, out Hit hit) {
+    vec3 size = vec3(scene.size);
+    vec3 dir = mix(ray.dir, vec3(1e-8), equal(ray.dir, vec3(0.0)));
+    vec3 inv_dir = 1.0 / dir;
+
+    vec3 t_near = min(-ray.origin * inv_dir, (size - ray.origin) * inv_dir);
+    vec3 t_far = max(-ray.origin * inv_dir, (size - ray.origin) * inv_dir);
+    float t = max(max(t_near.x, t_near.y), max(t_near.z, 0.0));
+    float t_exit = min(min(t_far.x, t_far.y), t_far.z);
+
+    if (t >= t_exit) {
+        return false;
+    }
+
+    ivec3 step = ivec3(sign(dir));
+    ivec3 cell = clamp(ivec3(floor(ray.origin + dir * t)), ivec3(0), ivec3(scene.size) - 1);
+    vec3 t_delta = abs(inv_dir);
+    vec3 t_max = (vec3(cell) + max(vec3(step), 0.0) - ray.origin) * inv_dir;
+
+    vec3 normal = vec3(0.0);
+    if (t > 0.0) {
+        if (t == t_near.x) normal = vec3(-step.x, 0.0, 0.0);
+        else if (t == t_near.y) normal = vec3(0.0, -step.y, 0.0);
+        else normal = vec3(0.0, 0.0, -step.z);
+    }
+
+    uint max_steps = scene.size.x + scene.size.y + scene.size.z;
+    for (uint i = 0u; i < max_steps; i++) {
+        uint material = voxel(cell);
+        if (material != 0) {
+            hit = Hit(material, normal, t);
+            return true;
+        }
+
+        if (t_max.x < t_max.y && t_max.x < t_max.z) {
+            cell.x += step.x;
+            t = t_max.x;
+            t_max.x += t_delta.x;
+            normal = vec3(-step.x, 0.0, 0.0);
+        } else if (t_max.y < t_max.z) {
+            cell.y += step.y;
+            t = t_max.y;
+            t_max.y += t_delta.y;
+            normal = vec3(0.0, -step.y, 0.0);
+        } else {
+            cell.z += step.z;
+            t = t_max.z;
+            t_max.z += t_delta.z;
+            normal = vec3(0.0, 0.0, -step.z);
+        }
+
+        if (any(lessThan(cell, ivec3(0))) || any(greaterThanEqual(cell, ivec3(scene.size)))) {
+            return false;
+        }
+    }
+
+    return false;
+}
+
+#endif
@@ -0,0 +1,24 @@
+#ifndef IMAGE_GLSL
+#define IMAGE_GLSL
+
+// Output image of the render shaders, one invocation per pixel in row-major order. The local
+// size must be declared before this file is included.
+layout (binding = 0) buffer Image { vec4 image[]; };
+
+uvec2 image_pos() {
+    return gl_GlobalInvocationID.xy;
+}
+
+uvec2 image_size() {
+    return gl_NumWorkGroups.xy * gl_WorkGroupSize.xy;
+}
+
+uint image_index(uvec2 pos) {
+    return pos.y * image_size().x + pos.x;
+}
+
+void image_store(vec4 color) {
+    image[image_index(image_pos())] = color;
+}
+
+#endif
@@ -0,0 +1,102 @@
+#ifndef OCTREE_GLSL
+#define OCTREE_GLSL
+
+#include "scene.glsl"
+
+// Sparse voxel octree traversal over the node layout written by `Octree` in `world/octree.rs`:
+// every node is 8 words, one per octant (x | y << 1 | z << 2), holding 0 for empty space,
+// OCTREE_LEAF | material for a uniform region, or the index of the child node.
+const uint OCTREE_LEAF = 1u << 31;
+
+uint octree_root_size() {
+    uint max_size = max(max(scene.size.x, scene.size.y), scene.size.z);
+    uint root_size = 2u;
+    while (root_size < max_size) {
+        root_size <<= 1;
+    }
+    return root_size;
+}
+
+// Finds the deepest node word containing `pos`, returning it along with the size of its region.
+uint octree_lookup(uvec3 pos, uint root_size, out uint region_size) {
+    uint word = 0u;
+    region_size = root_size;
+
+    while (region_size > 1u) {
+        region_size >>= 1;
+        uvec3 octant = uvec3(notEqual(pos & uvec3(region_size), uvec3(0u)));
+        word = scene_data[word * 8u + (octant.x | (octant.y << 1) | (octant.z << 2))];
+
+        if (word == 0u || (word & OCTREE_LEAF) != 0u) {
+            break;
+        }
+    }
+
+    return word;
+}
+
+// Steps through the leaves of the octree, skipping each empty region in a single step.
+bool trace_scene(Ray ray, out Hit hit) {
+    vec3 size = vec3(scene.size);
+    vec3 dir = mix(ray.dir, vec3(1e-8), equal(ray.dir, vec3(0.0)));
+    vec3 inv_dir = 1.0 / dir;
+
+    vec3 t_near = min(-ray.origin * inv_dir, (size - ray.origin) * inv_dir);
+    vec3 t_far = max(-ray.origin * inv_dir, (size - ray.origin) * inv_dir);
+    float t = max(max(t_near.x, t_near.y), max(t_near.z, 0.0));
+    float t_exit = min(min(t_far.x, t_far.y), t_far.z);
+
+    if (t >= t_exit) {
+        return false;
+    }
+
+    ivec3 step = ivec3(sign(dir));
+    ivec3 cell = clamp(ivec3(floor(ray.origin + dir * t)), ivec3(0), ivec3(scene.size) - 1);
+
+    vec3 normal = vec3(0.0);
+    if (t > 0.0) {
+        if (t == t_near.x) normal = vec3(-step.x, 0.0, 0.0);
+        else if (t == t_near.y) normal = vec3(0.0, -step.y, 0.0);
+        else normal = vec3(0.0, 0.0, -step.z);
+    }
+
+    uint root_size = octree_root_size();
+    uint max_steps = 3u * root_size;
+    for (uint i = 0u; i < max_steps; i++) {
+        uint region_size;
+        uint word = octree_lookup(uvec3(cell), root_size, region_size);
+
+        if (word != 0u) {
+            hit = Hit(word & ~OCTREE_LEAF, normal, t);
+            return true;
+        }
+
+        ivec3 region_min = cell & ~ivec3(region_size - 1u);
+        vec3 region_exit = vec3(region_min) + vec3(greaterThan(step, ivec3(0))) * float(region_size);
+        vec3 t_max = (region_exit - ray.origin) * inv_dir;
+
+        int axis;
+        if (t_max.x < t_max.y && t_max.x < t_max.z) {
+            axis = 0;
+        } else if (t_max.y < t_max.z) {
+            axis = 1;
+        } else {
+            axis = 2;
+        }
+
+        t = t_max[axis];
+        cell = ivec3(floor(ray.origin + dir * t));
+        cell[axis] = step[axis] > 0 ? region_min[axis] + int(region_size) : region_min[axis] - 1;
+
+        normal = vec3(0.0);
+        normal[axis] = -float(step[axis]);
+
+        if (any(lessThan(cell, ivec3(0))) || any(greaterThanEqual(cell, ivec3(scene.size)))) {
+            return false;
+        }
+    }
+
+    return false;
+}
+
+#endif
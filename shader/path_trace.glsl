@@ -0,0 +1,70 @@
+layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+#include "random.glsl"
+#include "scene.glsl"
+
+#ifndef MAX_BOUNCES
+#define MAX_BOUNCES 4
+#endif
+
+const float PI = 3.14159265358979;
+
+struct Frame {
+    uint seed;
+    uint samples;
+    uint accumulate;
+};
+
+// Running sum of radiance in rgb and the number of samples in w.
+layout (binding = 0) buffer Accumulation { vec4 accumulation[]; };
+layout (push_constant) uniform FrameConstants { Frame frame; };
+
+vec3 cosine_sample_hemisphere(vec3 normal, inout uint rng) {
+    float phi = 2.0 * PI * random(rng);
+    float r2 = random(rng);
+    float r = sqrt(r2);
+
+    vec3 tangent = normalize(cross(normal, abs(normal.x) > 0.5 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0)));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * (r * cos(phi)) + bitangent * (r * sin(phi)) + normal * sqrt(1.0 - r2));
+}
+
+vec3 trace_path(Ray ray, inout uint rng) {
+    vec3 radiance = vec3(0.0);
+    vec3 throughput = vec3(1.0);
+
+    for (uint bounce = 0u; bounce <= MAX_BOUNCES; bounce++) {
+        Hit hit;
+        if (!trace_scene(ray, hit)) {
+            radiance += throughput * sky(ray.dir);
+            break;
+        }
+
+        Material material = materials[hit.material];
+        radiance += throughput * material.color * material.properties.x;
+        throughput *= material.color;
+
+        vec3 normal = hit.normal == vec3(0.0) ? -ray.dir : hit.normal;
+        vec3 origin = ray.origin + ray.dir * hit.t + normal * 1e-3;
+        ray = Ray(origin, cosine_sample_hemisphere(normal, rng));
+    }
+
+    return radiance;
+}
+
+void main() {
+    uvec2 pos = gl_GlobalInvocationID.xy;
+    uvec2 size = gl_NumWorkGroups.xy * gl_WorkGroupSize.xy;
+    uint idx = pos.y * size.x + pos.x;
+
+    uint rng = pcg_hash(idx ^ pcg_hash(frame.seed));
+
+    vec3 sum = vec3(0.0);
+    for (uint i = 0u; i < frame.samples; i++) {
+        vec2 jitter = vec2(random(rng), random(rng));
+        sum += trace_path(camera_ray_at(camera, vec2(pos) + jitter, size), rng);
+    }
+
+    vec4 value = vec4(sum, float(frame.samples));
+    accumulation[idx] = frame.accumulate != 0u ? accumulation[idx] + value : value;
+}
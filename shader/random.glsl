@@ -0,0 +1,17 @@
+#ifndef RANDOM_GLSL
+#define RANDOM_GLSL
+
+// PCG hash (Jarzynski & Olano, "Hash Functions for GPU Rendering").
+uint pcg_hash(uint value) {
+    uint state = value * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Uniform random float in [0, 1), advancing `state`.
+float random(inout uint state) {
+    state = pcg_hash(state);
+    return float(state >> 8) / 16777216.0;
+}
+
+#endif
@@ -0,0 +1,9 @@
+#ifndef RAY_GLSL
+#define RAY_GLSL
+
+struct Ray {
+    vec3 origin;
+    vec3 dir;
+};
+
+#endif
@@ -0,0 +1,32 @@
+#ifndef SCENE_GLSL
+#define SCENE_GLSL
+
+#include "camera.glsl"
+
+struct Scene {
+    uvec3 size;
+};
+
+struct Material {
+    vec3 color;
+    vec4 properties; // x: emission strength
+};
+
+struct Hit {
+    uint material;
+    vec3 normal;
+    float t;
+};
+
+layout (binding = 1) uniform CameraBuffer { Camera camera; };
+layout (binding = 2) buffer SceneBuffer { Scene scene; uint scene_data[]; };
+layout (binding = 3) buffer MaterialBuffer { Material materials[]; };
+
+// Implemented by the traversal included after the main shader, see `dda.glsl` and `octree.glsl`.
+bool trace_scene(Ray ray, out Hit hit);
+
+vec3 sky(vec3 dir) {
+    return mix(vec3(0.9, 0.9, 0.95), vec3(0.4, 0.6, 0.9), clamp(dir.y, 0.0, 1.0));
+}
+
+#endif
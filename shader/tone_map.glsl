@@ -0,0 +1,62 @@
+#version 460
+
+layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+struct ToneMapping {
+    float exposure;
+    uint tone_operator;
+    uint srgb;
+};
+
+layout (binding = 0) buffer Input { vec4 hdr[]; };
+layout (binding = 1) buffer Output { vec4 ldr[]; };
+layout (binding = 2) buffer Properties { ToneMapping tone_mapping; };
+
+// Must match `ToneMapOperator` in `tone_map.rs`.
+const uint CLAMP = 0u;
+const uint REINHARD = 1u;
+const uint ACES = 2u;
+const uint FILMIC = 3u;
+
+vec3 aces(vec3 c) {
+    return (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14);
+}
+
+vec3 filmic_curve(vec3 x) {
+    const float A = 0.15, B = 0.50, C = 0.10, D = 0.20, E = 0.02, F = 0.30;
+    return (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F;
+}
+
+vec3 filmic(vec3 c) {
+    const float WHITE_POINT = 11.2;
+    return filmic_curve(2.0 * c) / filmic_curve(vec3(WHITE_POINT));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(12.92 * c, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, greaterThan(c, vec3(0.0031308)));
+}
+
+void main() {
+    uvec2 pos = gl_GlobalInvocationID.xy;
+    uvec2 size = gl_NumWorkGroups.xy * gl_WorkGroupSize.xy;
+    uint idx = pos.y * size.x + pos.x;
+
+    vec4 value = hdr[idx];
+    vec3 color = max(value.rgb * tone_mapping.exposure, 0.0);
+
+    if (tone_mapping.tone_operator == REINHARD) {
+        color = color / (1.0 + color);
+    } else if (tone_mapping.tone_operator == ACES) {
+        color = aces(color);
+    } else if (tone_mapping.tone_operator == FILMIC) {
+        color = filmic(color);
+    }
+
+    color = clamp(color, 0.0, 1.0);
+
+    if (tone_mapping.srgb != 0u) {
+        color = linear_to_srgb(color);
+    }
+
+    ldr[idx] = vec4(color, clamp(value.a, 0.0, 1.0));
+}
@@ -0,0 +1,22 @@
+layout (local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+#include "image.glsl"
+#include "scene.glsl"
+
+const vec3 LIGHT_DIR = normalize(vec3(0.4, 1.0, 0.6));
+const float AMBIENT = 0.3;
+
+void main() {
+    Ray ray = camera_ray(camera, image_pos(), image_size());
+    Hit hit;
+
+    vec3 color;
+    if (trace_scene(ray, hit)) {
+        float diffuse = max(dot(hit.normal, LIGHT_DIR), 0.0);
+        color = materials[hit.material].color * (AMBIENT + (1.0 - AMBIENT) * diffuse);
+    } else {
+        color = sky(ray.dir);
+    }
+
+    image_store(vec4(color, 1.0));
+}
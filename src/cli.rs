@@ -0,0 +1,837 @@
+use super::output::{save_exr, save_png};
+use super::preamble::*;
+use super::renderer::{Accumulation, Renderer, OCTREE_SHADER, PATH_TRACE_SHADER, VOXEL_SHADER};
+use super::tone_map::{ToneMapOperator, ToneMapStage, ToneMapping, TONE_MAP_SHADER};
+use super::world::{Camera, Octree, SceneKind, VoxError, VoxScene};
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub const USAGE: &str = "\
+usage: voxel_renderer <scene.vox> [options]
+       voxel_renderer compile [<shader.glsl>...] [compile options]
+
+options:
+  -o, --output <path>         output image, .exr is saved as linear HDR (default: render.png)
+  -s, --size <width>x<height> image size in pixels (default: 1280x720)
+  -n, --samples <count>       samples per pixel, path renderer only (default: 64)
+  -r, --renderer <name>       path, voxel or octree (default: path)
+  --camera <x,y,z>            camera position (default: looking at the scene from the front)
+  --target <x,y,z>            point the camera looks at (default: scene centre)
+  --focal-distance <value>    focal distance for a sensor of height 1 (default: 1)
+  --exposure <value>          exposure multiplier applied before tone mapping (default: 1)
+  --tone-map <name>           clamp, reinhard, aces or filmic (default: aces)
+  --cache <dir>               shader and pipeline cache (default: $VOXEL_RENDERER_CACHE or
+                              voxel_renderer in the user cache directory)
+  --no-cache                  compile shaders without reading or writing a cache
+  --spirv <dir>               load the shaders written by compile from <dir> instead of
+                              compiling them
+  -h, --help                  print this message
+
+compile options:
+  -o, --output <dir>          directory the .spv files are written to (default: .)
+  -O, --optimize              optimize the SPIR-V for performance
+  -D, --define <name>[=value] define a preprocessor macro
+
+without any shaders, compile writes the built-in voxel, octree, path_trace and tone_map shaders";
+
+/// Samples per [`Renderer::accumulate`] call, kept low so no single submission runs long
+/// enough to trip the driver's timeout.
+const SAMPLES_PER_FRAME: u32 = 8;
+
+/// Options that take a value, see [`USAGE`].
+const OPTIONS: &[&str] = &[
+    "-o",
+    "--output",
+    "-s",
+    "--size",
+    "-n",
+    "--samples",
+    "-r",
+    "--renderer",
+    "--camera",
+    "--target",
+    "--focal-distance",
+    "--exposure",
+    "--tone-map",
+    "--cache",
+    "--spirv",
+];
+
+/// Options of the compile command that take a value, see [`USAGE`].
+const COMPILE_OPTIONS: &[&str] = &["-o", "--output", "-D", "--define"];
+
+/// Shaders written by the compile command when no files are given, so they can be loaded with
+/// [`Program::from_spirv_file`] instead of being compiled at startup.
+const BUILTIN_SHADERS: &[(&str, &str)] = &[
+    ("voxel", VOXEL_SHADER),
+    ("octree", OCTREE_SHADER),
+    ("path_trace", PATH_TRACE_SHADER),
+    ("tone_map", TONE_MAP_SHADER),
+];
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("missing scene file")]
+    MissingScene,
+    #[error("unexpected argument \"{0}\"")]
+    UnexpectedArgument(String),
+    #[error("unknown option \"{0}\"")]
+    UnknownOption(String),
+    #[error("missing value for \"{0}\"")]
+    MissingValue(String),
+    #[error("invalid value \"{1}\" for \"{0}\"")]
+    InvalidValue(String, String),
+    #[error("\"{0}\" only applies to the path renderer")]
+    PathRendererOnly(String),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RendererKind {
+    Path,
+    Voxel,
+    Octree,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderArgs {
+    pub scene: PathBuf,
+    pub output: PathBuf,
+    pub image_size: glam::UVec2,
+    pub samples: u32,
+    pub renderer: RendererKind,
+    pub camera: Option<glam::Vec3>,
+    pub target: Option<glam::Vec3>,
+    pub focal_distance: f32,
+    pub tone_mapping: ToneMapping,
+    /// Cache directory given with `--cache`, see [`render`] for the default.
+    pub cache: Option<PathBuf>,
+    pub no_cache: bool,
+    /// Directory of precompiled shaders given with `--spirv`.
+    pub spirv: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompileArgs {
+    pub shaders: Vec<PathBuf>,
+    pub output: PathBuf,
+    pub optimize: bool,
+    pub defines: Vec<(String, Option<String>)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Render(RenderArgs),
+    Compile(CompileArgs),
+    Help,
+}
+
+impl Command {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+        let mut args = args.into_iter().peekable();
+        if args.next_if(|arg| arg == "compile").is_some() {
+            return Self::parse_compile(args);
+        }
+
+        let mut scene = None;
+        let mut samples_option = None;
+        let mut render_args = RenderArgs {
+            scene: PathBuf::new(),
+            output: PathBuf::from("render.png"),
+            image_size: glam::uvec2(1280, 720),
+            samples: 64,
+            renderer: RendererKind::Path,
+            camera: None,
+            target: None,
+            focal_distance: 1.0,
+            tone_mapping: ToneMapOperator::Aces.into(),
+            cache: None,
+            no_cache: false,
+            spirv: None,
+        };
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                match scene {
+                    None => scene = Some(PathBuf::from(arg)),
+                    Some(_) => return Err(CliError::UnexpectedArgument(arg)),
+                }
+                continue;
+            }
+
+            if arg == "-h" || arg == "--help" {
+                return Ok(Command::Help);
+            }
+
+            if arg == "--no-cache" {
+                render_args.no_cache = true;
+                continue;
+            }
+
+            if !OPTIONS.contains(&arg.as_str()) {
+                return Err(CliError::UnknownOption(arg));
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+            let invalid = || CliError::InvalidValue(arg.clone(), value.clone());
+
+            match arg.as_str() {
+                "-o" | "--output" => render_args.output = PathBuf::from(&value),
+                "-s" | "--size" => {
+                    render_args.image_size = parse_size(&value).ok_or_else(invalid)?;
+                }
+                "-n" | "--samples" => {
+                    samples_option = Some(arg.clone());
+                    render_args.samples = value
+                        .parse()
+                        .ok()
+                        .filter(|&samples| samples > 0)
+                        .ok_or_else(invalid)?;
+                }
+                "-r" | "--renderer" => {
+                    render_args.renderer = match value.as_str() {
+                        "path" => RendererKind::Path,
+                        "voxel" => RendererKind::Voxel,
+                        "octree" => RendererKind::Octree,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "--camera" => render_args.camera = Some(parse_vec3(&value).ok_or_else(invalid)?),
+                "--target" => render_args.target = Some(parse_vec3(&value).ok_or_else(invalid)?),
+                "--focal-distance" => {
+                    render_args.focal_distance = parse_positive(&value).ok_or_else(invalid)?;
+                }
+                "--exposure" => {
+                    render_args.tone_mapping.exposure =
+                        parse_positive(&value).ok_or_else(invalid)?;
+                }
+                "--tone-map" => {
+                    render_args.tone_mapping.operator = match value.as_str() {
+                        "clamp" => ToneMapOperator::Clamp,
+                        "reinhard" => ToneMapOperator::Reinhard,
+                        "aces" => ToneMapOperator::Aces,
+                        "filmic" => ToneMapOperator::Filmic,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "--cache" => render_args.cache = Some(PathBuf::from(&value)),
+                "--spirv" => render_args.spirv = Some(PathBuf::from(&value)),
+                _ => unreachable!(),
+            }
+        }
+
+        render_args.scene = scene.ok_or(CliError::MissingScene)?;
+        if let Some(option) = samples_option {
+            if render_args.renderer != RendererKind::Path {
+                return Err(CliError::PathRendererOnly(option));
+            }
+        }
+        Ok(Command::Render(render_args))
+    }
+
+    fn parse_compile(mut args: impl Iterator<Item = String>) -> Result<Command, CliError> {
+        let mut compile_args = CompileArgs {
+            shaders: Vec::new(),
+            output: PathBuf::from("."),
+            optimize: false,
+            defines: Vec::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                compile_args.shaders.push(PathBuf::from(arg));
+                continue;
+            }
+
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-O" | "--optimize" => {
+                    compile_args.optimize = true;
+                    continue;
+                }
+                _ if !COMPILE_OPTIONS.contains(&arg.as_str()) => {
+                    return Err(CliError::UnknownOption(arg))
+                }
+                _ => (),
+            }
+
+            let value = args
+                .next()
+                .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+
+            match arg.as_str() {
+                "-o" | "--output" => compile_args.output = PathBuf::from(&value),
+                "-D" | "--define" => {
+                    let (name, definition) = match value.split_once('=') {
+                        Some((name, definition)) => (name, Some(definition.to_string())),
+                        None => (value.as_str(), None),
+                    };
+
+                    if name.is_empty() {
+                        return Err(CliError::InvalidValue(arg, value));
+                    }
+                    compile_args.defines.push((name.to_string(), definition));
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Ok(Command::Compile(compile_args))
+    }
+}
+
+/// Renders the scene described by `args` and writes it to `args.output`.
+///
+/// Shaders are cached in `--cache`, or in [`default_cache_directory`] if neither `--cache` nor
+/// `--no-cache` is given. With `--spirv` they are loaded from the output of [`compile`] instead.
+pub fn render(args: &RenderArgs) -> Result<()> {
+    let scene = VoxScene::load(&args.scene)?;
+    let grid = &scene.grid;
+
+    let size = grid.size().as_vec3();
+    let target = args.target.unwrap_or(size / 2.0);
+    let position = args
+        .camera
+        .unwrap_or(target + glam::vec3(0.6, 0.5, 1.0) * size.max_element() * 1.5);
+
+    let mut camera = Camera::new(
+        position,
+        glam::Vec3::ZERO,
+        glam::vec2(args.image_size.x as f32 / args.image_size.y as f32, 1.0),
+        args.focal_distance,
+    );
+    camera.look_at(target);
+
+    let instance = build_instance(args)?;
+    let renderer = build_renderer(instance.clone(), args)?;
+
+    let image = match args.renderer {
+        RendererKind::Path => {
+            let mut accumulation = Accumulation::new(&instance, args.image_size)?;
+
+            let mut remaining = args.samples;
+            while remaining > 0 {
+                let samples = remaining.min(SAMPLES_PER_FRAME);
+                renderer.accumulate(&mut accumulation, &camera, grid, &scene.materials, samples)?;
+                remaining -= samples;
+            }
+
+            accumulation.mean()?
+        }
+        RendererKind::Voxel => {
+            renderer.render_scene_hdr(&camera, grid, &scene.materials, args.image_size)?
+        }
+        RendererKind::Octree => renderer.render_scene_hdr(
+            &camera,
+            &Octree::from_grid(grid)?,
+            &scene.materials,
+            args.image_size,
+        )?,
+    };
+
+    if is_exr(&args.output) {
+        save_exr(&image, &args.output)
+    } else {
+        save_png(&image, &args.output, &args.tone_mapping)
+    }
+}
+
+/// Compiles the shaders listed in `args` to SPIR-V and writes each one to
+/// `<output>/<file stem>.spv`, printing the written paths.
+pub fn compile(args: &CompileArgs) -> Result<()> {
+    let shaders = if args.shaders.is_empty() {
+        BUILTIN_SHADERS
+            .iter()
+            .map(|(name, source)| {
+                (
+                    name.to_string(),
+                    format!("{}.glsl", name),
+                    source.to_string(),
+                )
+            })
+            .collect()
+    } else {
+        args.shaders
+            .iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .ok_or_else(|| anyhow!("invalid shader path \"{}\"", path.display()))?;
+                let source = std::fs::read_to_string(path)
+                    .map_err(|e| anyhow!("failed to read \"{}\": {}", path.display(), e))?;
+                Ok((
+                    name.to_string_lossy().into_owned(),
+                    path.display().to_string(),
+                    source,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    std::fs::create_dir_all(&args.output)?;
+
+    for (name, file_name, source) in shaders {
+        let mut builder = ProgramBuilder::new(source, file_name, "main");
+        if args.optimize {
+            builder = builder.optimization_level(OptimizationLevel::Performance);
+        }
+        for (macro_name, value) in &args.defines {
+            builder = match value {
+                Some(value) => builder.define(macro_name, value),
+                None => builder.flag(macro_name),
+            };
+        }
+
+        let path = args.output.join(name).with_extension("spv");
+        std::fs::write(&path, spirv_to_bytes(&builder.compile()?))?;
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Process exit code for an error returned by [`Command::parse`], [`render`] or [`compile`].
+/// The first error in the chain with a known type decides the code.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    for cause in error.chain() {
+        if cause.is::<CliError>() {
+            return 2;
+        }
+        if cause.is::<VoxError>() {
+            return 3;
+        }
+        if cause.is::<InstanceError>() {
+            return 4;
+        }
+        if cause.is::<ProgramError>() {
+            return 5;
+        }
+        if cause.is::<TaskError>() || cause.is::<BufferError>() {
+            return 6;
+        }
+        if cause.is::<image::ImageError>() {
+            return 7;
+        }
+    }
+
+    1
+}
+
+/// Message for an error returned by [`render`] or [`compile`]. Shader compilation errors are
+/// rendered after the context they were returned with, each with the offending source line when
+/// the file is in the built-in shader library or can be found on disk.
+pub fn error_report(error: &anyhow::Error) -> String {
+    let diagnostics = error
+        .chain()
+        .find_map(|cause| match cause.downcast_ref::<ProgramError>() {
+            Some(ProgramError::CompilationFailed(diagnostics)) => Some(diagnostics),
+            _ => None,
+        });
+
+    let Some(diagnostics) = diagnostics.filter(|d| !d.is_empty()) else {
+        return format!("error: {:#}", error);
+    };
+
+    // the compilation error's own message lists the diagnostics, which are rendered below
+    let context: Vec<String> = error
+        .chain()
+        .take_while(|cause| !cause.is::<ProgramError>())
+        .map(|cause| cause.to_string())
+        .collect();
+
+    // built-in names are relative and could match an unrelated file in the working directory
+    let builtin = ShaderIncludes::builtin();
+    let rendered = diagnostics.iter().map(|diagnostic| {
+        let source = diagnostic.file.as_ref().and_then(|file| {
+            builtin
+                .source(file)
+                .map(str::to_string)
+                .or_else(|| std::fs::read_to_string(file).ok())
+        });
+        diagnostic.render(source.as_deref())
+    });
+
+    let mut report = Vec::new();
+    if !context.is_empty() {
+        report.push(format!("error: {}", context.join(": ")));
+    }
+    report.extend(rendered);
+    report.join("\n\n")
+}
+
+/// Builds the renderer for `args.renderer`, loading `<name>.spv` and `tone_map.spv` from the
+/// `--spirv` directory if one is given, see [`BUILTIN_SHADERS`].
+fn build_renderer(instance: Instance, args: &RenderArgs) -> Result<Renderer> {
+    let Some(directory) = &args.spirv else {
+        return match args.renderer {
+            RendererKind::Path => Renderer::path_tracer(instance),
+            RendererKind::Voxel => Renderer::voxel(instance),
+            RendererKind::Octree => Renderer::octree(instance),
+        };
+    };
+
+    let (name, scene_kind) = match args.renderer {
+        RendererKind::Path => ("path_trace", SceneKind::VoxelGrid),
+        RendererKind::Voxel => ("voxel", SceneKind::VoxelGrid),
+        RendererKind::Octree => ("octree", SceneKind::Octree),
+    };
+
+    let load = |name: &str| {
+        let path = directory.join(name).with_extension("spv");
+        // SAFETY: the directory is trusted like the executable, it holds what `compile` wrote
+        unsafe { Program::from_spirv_file(&instance, &path, "main") }
+            .with_context(|| format!("failed to load \"{}\"", path.display()))
+    };
+    let render_program = load(name)?;
+    let tone_map_stage = ToneMapStage::from_program(load("tone_map")?);
+
+    Ok(Renderer::from_parts(instance, render_program, tone_map_stage).for_scene(scene_kind))
+}
+
+/// Builds the instance with the cache directory of `args`. Failing to create the default cache
+/// directory only disables the cache, since it wasn't asked for.
+fn build_instance(args: &RenderArgs) -> Result<Instance> {
+    if args.no_cache {
+        return Ok(InstanceBuilder::new().build()?);
+    }
+
+    if let Some(directory) = &args.cache {
+        return Ok(InstanceBuilder::new().cache_directory(directory).build()?);
+    }
+
+    let Some(directory) = default_cache_directory() else {
+        return Ok(InstanceBuilder::new().build()?);
+    };
+
+    match InstanceBuilder::new().cache_directory(&directory).build() {
+        Err(InstanceError::CacheCreationFailed(_)) => {
+            eprintln!(
+                "warning: failed to create shader cache in \"{}\", shaders are not cached",
+                directory.display()
+            );
+            Ok(InstanceBuilder::new().build()?)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Cache directory used without `--cache`: `$VOXEL_RENDERER_CACHE`, `$XDG_CACHE_HOME` or
+/// `~/.cache`, in that order.
+fn default_cache_directory() -> Option<PathBuf> {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    var("VOXEL_RENDERER_CACHE")
+        .or_else(|| var("XDG_CACHE_HOME").map(|dir| dir.join("voxel_renderer")))
+        .or_else(|| var("HOME").map(|dir| dir.join(".cache").join("voxel_renderer")))
+}
+
+fn is_exr(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("exr"))
+}
+
+fn parse_size(value: &str) -> Option<glam::UVec2> {
+    let (width, height) = value.split_once('x')?;
+    let size = glam::uvec2(width.parse().ok()?, height.parse().ok()?);
+    (size.x > 0 && size.y > 0).then_some(size)
+}
+
+fn parse_vec3(value: &str) -> Option<glam::Vec3> {
+    let components = value
+        .split(',')
+        .map(|c| c.trim().parse::<f32>().ok().filter(|c| c.is_finite()))
+        .collect::<Option<Vec<_>>>()?;
+
+    match components[..] {
+        [x, y, z] => Some(glam::vec3(x, y, z)),
+        _ => None,
+    }
+}
+
+fn parse_positive(value: &str) -> Option<f32> {
+    value
+        .parse()
+        .ok()
+        .filter(|&value: &f32| value.is_finite() && value > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, CliError> {
+        Command::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn defaults() {
+        let Command::Render(args) = parse(&["scene.vox"]).unwrap() else {
+            panic!("expected a render command");
+        };
+
+        assert_eq!(args.scene, PathBuf::from("scene.vox"));
+        assert_eq!(args.output, PathBuf::from("render.png"));
+        assert_eq!(args.image_size, glam::uvec2(1280, 720));
+        assert_eq!(args.renderer, RendererKind::Path);
+        assert_eq!(args.camera, None);
+        assert_eq!(args.tone_mapping, ToneMapOperator::Aces.into());
+        assert_eq!(args.cache, None);
+        assert!(!args.no_cache);
+        assert_eq!(args.spirv, None);
+    }
+
+    #[test]
+    fn options() {
+        let command = parse(&[
+            "-o",
+            "out.exr",
+            "scene.vox",
+            "--size",
+            "320x240",
+            "-n",
+            "16",
+            "--renderer",
+            "path",
+            "--camera",
+            "1,2.5,-3",
+            "--target",
+            "0,0,0",
+            "--focal-distance",
+            "2",
+            "--exposure",
+            "0.5",
+            "--tone-map",
+            "filmic",
+            "--cache",
+            "cache",
+            "--spirv",
+            "spv",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            command,
+            Command::Render(RenderArgs {
+                scene: PathBuf::from("scene.vox"),
+                output: PathBuf::from("out.exr"),
+                image_size: glam::uvec2(320, 240),
+                samples: 16,
+                renderer: RendererKind::Path,
+                camera: Some(glam::vec3(1.0, 2.5, -3.0)),
+                target: Some(glam::Vec3::ZERO),
+                focal_distance: 2.0,
+                tone_mapping: ToneMapping {
+                    exposure: 0.5,
+                    operator: ToneMapOperator::Filmic,
+                    srgb: true,
+                },
+                cache: Some(PathBuf::from("cache")),
+                no_cache: false,
+                spirv: Some(PathBuf::from("spv")),
+            })
+        );
+
+        let Command::Render(args) = parse(&["scene.vox", "--no-cache"]).unwrap() else {
+            panic!("expected a render command");
+        };
+        assert!(args.no_cache);
+        assert_eq!(args.cache, None);
+        assert!(is_exr(Path::new("out.EXR")));
+        assert_eq!(parse(&["scene.vox", "-h"]).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(matches!(parse(&[]), Err(CliError::MissingScene)));
+        assert!(matches!(
+            parse(&["a.vox", "b.vox"]),
+            Err(CliError::UnexpectedArgument(_))
+        ));
+        assert!(matches!(
+            parse(&["a.vox", "--fast"]),
+            Err(CliError::UnknownOption(_))
+        ));
+        assert!(matches!(
+            parse(&["a.vox", "--size"]),
+            Err(CliError::MissingValue(_))
+        ));
+        for renderer in ["voxel", "octree"] {
+            assert!(matches!(
+                parse(&["a.vox", "-n", "16", "-r", renderer]),
+                Err(CliError::PathRendererOnly(_))
+            ));
+        }
+
+        for (option, value) in [
+            ("--size", "0x10"),
+            ("--size", "640"),
+            ("--samples", "0"),
+            ("--renderer", "raster"),
+            ("--camera", "1,2"),
+            ("--exposure", "-1"),
+            ("--tone-map", "linear"),
+        ] {
+            assert!(matches!(
+                parse(&["a.vox", option, value]),
+                Err(CliError::InvalidValue(_, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code(&CliError::MissingScene.into()), 2);
+        assert_eq!(exit_code(&VoxError::InvalidHeader.into()), 3);
+        assert_eq!(exit_code(&InstanceError::NoVulkanDevice.into()), 4);
+        assert_eq!(
+            exit_code(&anyhow::Error::from(TaskError::TaskSubmissionFailed).context("frame 3")),
+            6
+        );
+        assert_eq!(exit_code(&anyhow!("something else")), 1);
+    }
+
+    #[test]
+    fn error_reports() {
+        let path = std::env::temp_dir().join("voxel_renderer_error_report.glsl");
+        std::fs::write(&path, "#version 460\nvoid main() {\n    x = 1;\n}\n").unwrap();
+
+        let error = anyhow::Error::from(ProgramError::CompilationFailed(vec![Diagnostic {
+            file: Some(path.display().to_string()),
+            line: Some(3),
+            column: None,
+            severity: DiagnosticSeverity::Error,
+            message: "'x' : undeclared identifier".to_string(),
+        }]))
+        .context("failed to build program");
+
+        assert_eq!(
+            error_report(&error),
+            format!(
+                "error: failed to build program\n\n\
+                 error: 'x' : undeclared identifier\n --> {}:3\n  |\n3 |     x = 1;\n  |",
+                path.display()
+            )
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let builtin = Diagnostic {
+            file: Some("ray.glsl".to_string()),
+            line: Some(1),
+            column: None,
+            severity: DiagnosticSeverity::Error,
+            message: "unexpected token".to_string(),
+        };
+        let line = ShaderIncludes::builtin()
+            .source("ray.glsl")
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap();
+        assert_eq!(
+            error_report(&ProgramError::CompilationFailed(vec![builtin.clone()]).into()),
+            builtin.render(Some(line))
+        );
+
+        assert_eq!(
+            error_report(&anyhow!("something else")),
+            "error: something else"
+        );
+    }
+
+    #[test]
+    fn compile_options() {
+        assert_eq!(
+            parse(&["compile"]).unwrap(),
+            Command::Compile(CompileArgs {
+                shaders: Vec::new(),
+                output: PathBuf::from("."),
+                optimize: false,
+                defines: Vec::new(),
+            })
+        );
+
+        assert_eq!(
+            parse(&[
+                "compile",
+                "a.glsl",
+                "-O",
+                "--output",
+                "spv",
+                "-D",
+                "MAX_BOUNCES=8",
+                "b.glsl",
+                "--define",
+                "DEBUG",
+            ])
+            .unwrap(),
+            Command::Compile(CompileArgs {
+                shaders: vec![PathBuf::from("a.glsl"), PathBuf::from("b.glsl")],
+                output: PathBuf::from("spv"),
+                optimize: true,
+                defines: vec![
+                    ("MAX_BOUNCES".to_string(), Some("8".to_string())),
+                    ("DEBUG".to_string(), None),
+                ],
+            })
+        );
+
+        assert!(matches!(
+            parse(&["compile", "-D", "=1"]),
+            Err(CliError::InvalidValue(_, _))
+        ));
+        assert!(matches!(
+            parse(&["compile", "--size", "1x1"]),
+            Err(CliError::UnknownOption(_))
+        ));
+    }
+
+    #[test]
+    fn compile_builtin_shaders() {
+        let output = std::env::temp_dir().join(format!(
+            "voxel_renderer_compiled_shaders_{}",
+            std::process::id()
+        ));
+        compile(&CompileArgs {
+            shaders: Vec::new(),
+            output: output.clone(),
+            optimize: true,
+            defines: Vec::new(),
+        })
+        .unwrap();
+
+        let instance = Instance::new().unwrap();
+        for (name, _) in BUILTIN_SHADERS {
+            let path = output.join(name).with_extension("spv");
+            // SAFETY: the files were just written by `compile`
+            assert!(unsafe { Program::from_spirv_file(&instance, path, "main") }.is_ok());
+        }
+
+        for (renderer, scene_kind) in [
+            ("path", SceneKind::VoxelGrid),
+            ("voxel", SceneKind::VoxelGrid),
+            ("octree", SceneKind::Octree),
+        ] {
+            let spirv = output.display().to_string();
+            let Command::Render(args) =
+                parse(&["scene.vox", "--spirv", &spirv, "-r", renderer]).unwrap()
+            else {
+                panic!("expected a render command");
+            };
+            let renderer = build_renderer(instance.clone(), &args).unwrap();
+            assert_eq!(renderer.scene_kind(), Some(scene_kind));
+        }
+
+        let Command::Render(args) = parse(&["scene.vox", "--spirv", "missing"]).unwrap() else {
+            panic!("expected a render command");
+        };
+        assert!(build_renderer(instance, &args).is_err());
+
+        std::fs::remove_dir_all(&output).unwrap();
+    }
+}
@@ -17,6 +17,8 @@ pub enum BufferError {
     VulkanBufferWriteFailed,
     #[error("failed to create vulkan buffer")]
     VulkanBufferCreationFailed,
+    #[error("uniform buffer contents are {0} bytes, std140 requires a multiple of 16")]
+    NotStd140Sized(usize),
 }
 
 pub mod buffer_location {
@@ -68,6 +70,9 @@ where
 
     pub fn bind(&self, binding: u32) -> BufferBinding {
         BufferBinding {
+            binding,
+            kind: DescriptorKind::StorageBuffer,
+            size: self.buffer.size(),
             write_descriptor_set: vk::WriteDescriptorSet::buffer(binding, self.buffer.clone()),
         }
     }
@@ -143,9 +148,27 @@ where
 }
 
 pub struct BufferBinding {
+    pub(super) binding: u32,
+    pub(super) kind: DescriptorKind,
+    pub(super) size: u64,
     pub(super) write_descriptor_set: vk::WriteDescriptorSet,
 }
 
+impl BufferBinding {
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+
+    pub fn kind(&self) -> DescriptorKind {
+        self.kind
+    }
+
+    /// Size of the bound range in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
 fn create_vk_buffer<T>(
     instance: &Instance,
     usage: vk::BufferUsage,
@@ -170,8 +193,12 @@ where
     .map_err(|_| BufferError::VulkanBufferCreationFailed)?)
 }
 
+/// Every buffer can also hold the work group counts of [`TaskBuilder::run_program_indirect`].
 fn default_buffer_usage() -> vk::BufferUsage {
-    vk::BufferUsage::STORAGE_BUFFER | vk::BufferUsage::TRANSFER_SRC | vk::BufferUsage::TRANSFER_DST
+    vk::BufferUsage::STORAGE_BUFFER
+        | vk::BufferUsage::INDIRECT_BUFFER
+        | vk::BufferUsage::TRANSFER_SRC
+        | vk::BufferUsage::TRANSFER_DST
 }
 
 fn default_cpu_memory() -> vk::MemoryTypeFilter {
@@ -0,0 +1,344 @@
+use super::*;
+use shaderc;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 64 bit FNV-1a, used to key cache entries. It is stable across runs and platforms of the same
+/// byte order, unlike the randomly seeded `DefaultHasher`.
+pub(super) struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub(super) fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(super) fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Counts of [`ProgramBuilder::build`] calls that found their SPIR-V in the cache directory and
+/// those that had to compile it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub spirv_hits: u32,
+    pub spirv_misses: u32,
+    /// Whether pipeline cache data from an earlier run was loaded when the instance was built.
+    pub pipeline_cache_loaded: bool,
+}
+
+/// A file pulled in by `#include` while compiling, recorded so a cached module is only reused
+/// while every include still resolves to the same content.
+pub(super) struct Dependency {
+    pub(super) relative: bool,
+    pub(super) requesting: String,
+    pub(super) requested: String,
+    /// Path of the file on disk, or the name of a virtual file.
+    pub(super) resolved_name: String,
+    pub(super) hash: u64,
+}
+
+impl Dependency {
+    fn to_line(&self) -> String {
+        let include_type = if self.relative {
+            "relative"
+        } else {
+            "standard"
+        };
+        format!(
+            "{:016x}\t{}\t{}\t{}\t{}",
+            self.hash, include_type, self.requesting, self.requested, self.resolved_name
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Dependency> {
+        let mut fields = line.split('\t');
+        let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let relative = match fields.next()? {
+            "relative" => true,
+            "standard" => false,
+            _ => return None,
+        };
+
+        Some(Dependency {
+            relative,
+            requesting: fields.next()?.to_string(),
+            requested: fields.next()?.to_string(),
+            resolved_name: fields.next()?.to_string(),
+            hash,
+        })
+    }
+
+    fn is_current(&self, includes: &ShaderIncludes) -> bool {
+        let include_type = match self.relative {
+            true => shaderc::IncludeType::Relative,
+            false => shaderc::IncludeType::Standard,
+        };
+
+        includes
+            .resolve(&self.requested, include_type, &self.requesting)
+            .is_ok_and(|include| {
+                include.resolved_name == self.resolved_name
+                    && Fnv1a::hash(include.content.as_bytes()) == self.hash
+            })
+    }
+}
+
+/// SPIR-V modules keyed by a hash of their source and compile options, stored as `<key>.spv`
+/// next to a `<key>.deps` manifest, plus the vulkan pipeline cache of the device.
+///
+/// The pipeline cache is written back when the last [`Instance`] using it is dropped, or
+/// explicitly with [`Instance::save_pipeline_cache`].
+pub(super) struct ShaderCache {
+    directory: PathBuf,
+    pipeline_cache_path: PathBuf,
+    pub(super) pipeline_cache: Arc<vk::PipelineCache>,
+    stats: Mutex<CacheStats>,
+}
+
+impl ShaderCache {
+    pub(super) fn new(
+        device: &Arc<vk::Device>,
+        device_info: &DeviceInfo,
+        directory: PathBuf,
+    ) -> Result<ShaderCache, InstanceError> {
+        std::fs::create_dir_all(&directory)
+            .map_err(|_| InstanceError::CacheCreationFailed(directory.display().to_string()))?;
+
+        // the driver validates the header and ignores data from another device or driver
+        let pipeline_cache_path = directory.join(format!(
+            "pipeline_{:04x}_{:04x}.bin",
+            device_info.vendor_id, device_info.device_id
+        ));
+        let initial_data = std::fs::read(&pipeline_cache_path).unwrap_or_default();
+        let pipeline_cache_loaded = !initial_data.is_empty();
+
+        // SAFETY: the data was written by `save_pipeline_cache` or is rejected by the driver
+        let pipeline_cache = unsafe {
+            vk::PipelineCache::new(
+                device.clone(),
+                vk::PipelineCacheCreateInfo {
+                    initial_data,
+                    ..Default::default()
+                },
+            )
+        }
+        .map_err(|_| InstanceError::CacheCreationFailed(directory.display().to_string()))?;
+
+        Ok(ShaderCache {
+            directory,
+            pipeline_cache_path,
+            pipeline_cache,
+            stats: Mutex::new(CacheStats {
+                pipeline_cache_loaded,
+                ..Default::default()
+            }),
+        })
+    }
+
+    pub(super) fn stats(&self) -> CacheStats {
+        self.stats.lock().map(|stats| *stats).unwrap_or_default()
+    }
+
+    /// Returns the module stored under `key` and its includes if all of them are unchanged,
+    /// counting a hit or a miss.
+    pub(super) fn load_spirv(
+        &self,
+        key: u64,
+        includes: &ShaderIncludes,
+    ) -> Option<(Vec<u32>, Vec<Dependency>)> {
+        let entry = self.read_entry(key, includes);
+
+        if let Ok(mut stats) = self.stats.lock() {
+            match entry {
+                Some(_) => stats.spirv_hits += 1,
+                None => stats.spirv_misses += 1,
+            }
+        }
+
+        entry
+    }
+
+    fn read_entry(
+        &self,
+        key: u64,
+        includes: &ShaderIncludes,
+    ) -> Option<(Vec<u32>, Vec<Dependency>)> {
+        let manifest = std::fs::read_to_string(self.entry_path(key, "deps")).ok()?;
+        let mut lines = manifest.lines();
+        let spirv_hash = u64::from_str_radix(lines.next()?, 16).ok()?;
+
+        let dependencies = lines
+            .map(Dependency::from_line)
+            .collect::<Option<Vec<_>>>()?;
+        if !dependencies.iter().all(|d| d.is_current(includes)) {
+            return None;
+        }
+
+        let bytes = std::fs::read(self.entry_path(key, "spv")).ok()?;
+        if Fnv1a::hash(&bytes) != spirv_hash {
+            return None;
+        }
+
+        Some((spirv_from_bytes(&bytes)?, dependencies))
+    }
+
+    /// Stores a module, ignoring failures since the cache only saves work.
+    pub(super) fn store_spirv(&self, key: u64, spirv: &[u32], dependencies: &[Dependency]) {
+        let bytes = spirv_to_bytes(spirv);
+        let manifest = std::iter::once(format!("{:016x}", Fnv1a::hash(&bytes)))
+            .chain(dependencies.iter().map(Dependency::to_line))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // the manifest is written last so a partially written entry is never read
+        let _ = write_atomic(&self.entry_path(key, "spv"), &bytes)
+            .and_then(|_| write_atomic(&self.entry_path(key, "deps"), manifest.as_bytes()));
+    }
+
+    pub(super) fn save_pipeline_cache(&self) -> Result<(), InstanceError> {
+        let data = self
+            .pipeline_cache
+            .get_data()
+            .map_err(|_| InstanceError::PipelineCacheSaveFailed)?;
+        write_atomic(&self.pipeline_cache_path, &data)
+            .map_err(|_| InstanceError::PipelineCacheSaveFailed)
+    }
+
+    fn entry_path(&self, key: u64, extension: &str) -> PathBuf {
+        self.directory.join(format!("{:016x}.{}", key, extension))
+    }
+}
+
+impl Drop for ShaderCache {
+    fn drop(&mut self) {
+        let _ = self.save_pipeline_cache();
+    }
+}
+
+/// Writes to a temporary file first, so concurrent processes never see a partial file.
+fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension(format!("tmp{}", std::process::id()));
+    std::fs::write(&temp_path, data)?;
+    std::fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a() {
+        assert_eq!(Fnv1a::hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Fnv1a::hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(Fnv1a::hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn dependency_lines() {
+        let dependency = Dependency {
+            relative: true,
+            requesting: "shader/main glsl.glsl".to_string(),
+            requested: "camera.glsl".to_string(),
+            resolved_name: "shader/camera.glsl".to_string(),
+            hash: 0x1234,
+        };
+
+        let parsed = Dependency::from_line(&dependency.to_line()).unwrap();
+        assert!(parsed.relative);
+        assert_eq!(parsed.requesting, dependency.requesting);
+        assert_eq!(parsed.requested, dependency.requested);
+        assert_eq!(parsed.resolved_name, dependency.resolved_name);
+        assert_eq!(parsed.hash, dependency.hash);
+
+        assert!(Dependency::from_line("1234\tother\ta\tb\tc").is_none());
+        assert!(Dependency::from_line("1234\trelative\ta\tb").is_none());
+        assert!(Dependency::from_line("not a hash").is_none());
+    }
+
+    #[test]
+    fn shader_cache() {
+        let directory = std::env::temp_dir().join("voxel_renderer_shader_cache_test");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let code = r#"
+            #version 460
+            #include "value.glsl"
+            layout(binding = 0) buffer Data { uint data; };
+            void main() { data = VALUE; }
+        "#;
+        let includes = |value: u32| {
+            ShaderIncludes::new().file("value.glsl", format!("const uint VALUE = {};", value))
+        };
+
+        let run = |instance: &Instance, program: &Program| {
+            let buffer = CpuBuffer::from_vec(instance, vec![0u32]).unwrap();
+            TaskBuilder::new(instance)
+                .unwrap()
+                .run_program(program, (1, 1, 1), vec![buffer.bind(0)])
+                .unwrap()
+                .build_submit_and_wait()
+                .unwrap();
+            buffer.read().unwrap()[0]
+        };
+
+        {
+            let instance = InstanceBuilder::new()
+                .cache_directory(&directory)
+                .build()
+                .unwrap();
+            let builder = ProgramBuilder::new(code, "test.glsl", "main");
+
+            let program = builder
+                .clone()
+                .includes(includes(1))
+                .build(&instance)
+                .unwrap();
+            assert_eq!(run(&instance, &program), 1);
+            let program = builder
+                .clone()
+                .includes(includes(1))
+                .build(&instance)
+                .unwrap();
+            assert_eq!(run(&instance, &program), 1);
+            let program = builder.includes(includes(2)).build(&instance).unwrap();
+            assert_eq!(run(&instance, &program), 2);
+
+            let stats = instance.cache_stats().unwrap();
+            assert_eq!((stats.spirv_hits, stats.spirv_misses), (1, 2));
+            assert!(!stats.pipeline_cache_loaded);
+        }
+
+        let instance = InstanceBuilder::new()
+            .cache_directory(&directory)
+            .build()
+            .unwrap();
+        let program = ProgramBuilder::new(code, "test.glsl", "main")
+            .includes(includes(1))
+            .define("UNUSED", 1)
+            .build(&instance)
+            .unwrap();
+        assert_eq!(run(&instance, &program), 1);
+
+        let stats = instance.cache_stats().unwrap();
+        assert_eq!((stats.spirv_hits, stats.spirv_misses), (0, 1));
+        assert!(stats.pipeline_cache_loaded);
+        assert!(instance.save_pipeline_cache().is_ok());
+    }
+}
@@ -0,0 +1,193 @@
+use super::*;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+
+pub(super) const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<vk::DebugUtilsMessageSeverity> for DebugSeverity {
+    fn from(severity: vk::DebugUtilsMessageSeverity) -> Self {
+        if severity.intersects(vk::DebugUtilsMessageSeverity::ERROR) {
+            DebugSeverity::Error
+        } else if severity.intersects(vk::DebugUtilsMessageSeverity::WARNING) {
+            DebugSeverity::Warning
+        } else if severity.intersects(vk::DebugUtilsMessageSeverity::INFO) {
+            DebugSeverity::Info
+        } else {
+            DebugSeverity::Verbose
+        }
+    }
+}
+
+/// A message reported through `VK_EXT_debug_utils`, usually by the validation layer.
+#[derive(Clone, Debug)]
+pub struct DebugMessage {
+    pub severity: DebugSeverity,
+    /// Name of the check that produced the message, such as a validation VUID.
+    pub id_name: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for DebugMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.id_name {
+            Some(id_name) => write!(f, "[{:?}] {}: {}", self.severity, id_name, self.message),
+            None => write!(f, "[{:?}] {}", self.severity, self.message),
+        }
+    }
+}
+
+pub(super) type DebugCallback = Arc<dyn Fn(&DebugMessage) + Send + Sync>;
+
+/// Keeps the debug utils messenger alive and collects its messages until they are taken.
+pub(super) struct DebugMessenger {
+    _messenger: vk::DebugUtilsMessenger,
+    messages: Arc<Mutex<Vec<DebugMessage>>>,
+}
+
+impl DebugMessenger {
+    pub(super) fn new(
+        instance: &Arc<vk::Instance>,
+        min_severity: DebugSeverity,
+        callback: Option<DebugCallback>,
+    ) -> Result<DebugMessenger, InstanceError> {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let collector = messages.clone();
+        // panics are caught by vulkano before they cross the ffi boundary
+        let callback = callback.map(AssertUnwindSafe);
+
+        // SAFETY: the callback never calls into vulkan
+        let user_callback = unsafe {
+            vk::DebugUtilsMessengerCallback::new(move |severity, _, data| {
+                let message = DebugMessage {
+                    severity: severity.into(),
+                    id_name: data.message_id_name.map(str::to_string),
+                    message: data.message.to_string(),
+                };
+
+                if message.severity < min_severity {
+                    return;
+                }
+
+                if let Some(callback) = &callback {
+                    (callback.0)(&message);
+                }
+
+                if let Ok(mut messages) = collector.lock() {
+                    messages.push(message);
+                }
+            })
+        };
+
+        let messenger = vk::DebugUtilsMessenger::new(
+            instance.clone(),
+            vk::DebugUtilsMessengerCreateInfo {
+                message_severity: vk::DebugUtilsMessageSeverity::ERROR
+                    | vk::DebugUtilsMessageSeverity::WARNING
+                    | vk::DebugUtilsMessageSeverity::INFO
+                    | vk::DebugUtilsMessageSeverity::VERBOSE,
+                message_type: vk::DebugUtilsMessageType::GENERAL
+                    | vk::DebugUtilsMessageType::VALIDATION
+                    | vk::DebugUtilsMessageType::PERFORMANCE,
+                ..vk::DebugUtilsMessengerCreateInfo::user_callback(user_callback)
+            },
+        )
+        .map_err(|_| InstanceError::DebugMessengerCreationFailed)?;
+
+        Ok(DebugMessenger {
+            _messenger: messenger,
+            messages,
+        })
+    }
+
+    pub(super) fn messages(&self) -> Vec<DebugMessage> {
+        self.messages.lock().map(|m| m.clone()).unwrap_or_default()
+    }
+
+    pub(super) fn take_messages(&self) -> Vec<DebugMessage> {
+        self.messages
+            .lock()
+            .map(|mut m| std::mem::take(&mut *m))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severity_order() {
+        assert!(DebugSeverity::Error > DebugSeverity::Warning);
+        assert!(DebugSeverity::Warning > DebugSeverity::Info);
+        assert!(DebugSeverity::Info > DebugSeverity::Verbose);
+
+        let severity = vk::DebugUtilsMessageSeverity::WARNING | vk::DebugUtilsMessageSeverity::INFO;
+        assert_eq!(DebugSeverity::from(severity), DebugSeverity::Warning);
+    }
+
+    #[test]
+    fn no_validation_errors() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer buffer_1 { uint data[]; };
+            void main() { data[gl_GlobalInvocationID.x] *= 2; }
+        ";
+
+        let callback_messages = Arc::new(Mutex::new(0));
+        let counter = callback_messages.clone();
+
+        let instance = InstanceBuilder::new()
+            .debug(true)
+            .debug_severity(DebugSeverity::Warning)
+            .debug_callback(move |_| *counter.lock().unwrap() += 1)
+            .build()
+            .unwrap();
+
+        // without the layer nothing reports errors, and the test would pass without checking
+        if !instance.validation_enabled() {
+            eprintln!(
+                "skipping no_validation_errors: VK_LAYER_KHRONOS_validation is not available"
+            );
+            return;
+        }
+
+        let program = Program::new(&instance, code, "test", "main").unwrap();
+        let src = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+        let dst = GpuBuffer::<u32>::new(&instance, 4).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .copy_buffer(&src, &dst)
+            .unwrap()
+            .run_program(&program, (4, 1, 1), vec![dst.bind(0)])
+            .unwrap()
+            .copy_buffer(&dst, &src)
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        assert_eq!(src.read().unwrap(), [2, 4, 6, 8]);
+
+        let messages = instance.take_debug_messages();
+        assert!(messages
+            .iter()
+            .all(|m| m.severity >= DebugSeverity::Warning));
+        assert_eq!(messages.len(), *callback_messages.lock().unwrap());
+
+        let errors: Vec<_> = messages
+            .iter()
+            .filter(|m| m.severity == DebugSeverity::Error)
+            .collect();
+        assert!(errors.is_empty(), "{:#?}", errors);
+        assert!(instance.debug_messages().is_empty());
+    }
+}
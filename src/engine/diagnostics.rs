@@ -0,0 +1,259 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticSeverity {
+    Warning,
+    Error,
+}
+
+/// A warning or error reported by the shader compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Name of the shader or the resolved name of an included file.
+    pub file: Option<String>,
+    /// 1-based line number.
+    pub line: Option<u32>,
+    /// 1-based column number, shaderc only reports lines.
+    pub column: Option<u32>,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Parses the diagnostics in shaderc's output, which has one `file:line: severity: message`
+    /// per line. Lines that don't match are appended to the previous message and the
+    /// `N errors generated.` summary is dropped.
+    pub fn parse(text: &str) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+
+        for line in text.lines().map(str::trim_end).filter(|l| !l.is_empty()) {
+            if is_summary(line) {
+                continue;
+            }
+
+            match (parse_line(line), diagnostics.last_mut()) {
+                (Some(diagnostic), _) => diagnostics.push(diagnostic),
+                (None, Some(previous)) => {
+                    previous.message.push('\n');
+                    previous.message.push_str(line.trim());
+                }
+                (None, None) => diagnostics.push(Diagnostic {
+                    file: None,
+                    line: None,
+                    column: None,
+                    severity: DiagnosticSeverity::Error,
+                    message: line.trim().to_string(),
+                }),
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Formats the diagnostic for a terminal, followed by its location and, when `source` is
+    /// the file the diagnostic refers to, the offending line with a marker under the column.
+    pub fn render(&self, source: Option<&str>) -> String {
+        let severity = match self.severity {
+            DiagnosticSeverity::Warning => "warning",
+            DiagnosticSeverity::Error => "error",
+        };
+        let mut output = format!("{}: {}", severity, self.message);
+
+        let location = match (&self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => format!("{}:{}:{}", file, line, column),
+            (Some(file), Some(line), None) => format!("{}:{}", file, line),
+            (Some(file), None, _) => file.clone(),
+            (None, _, _) => return output,
+        };
+        output.push_str(&format!("\n --> {}", location));
+
+        let Some((line_number, text)) = self.line.zip(source).and_then(|(line, source)| {
+            let text = source.lines().nth(line.checked_sub(1)? as usize)?;
+            Some((line, text))
+        }) else {
+            return output;
+        };
+
+        let gutter = " ".repeat(line_number.to_string().len());
+        output.push_str(&format!(
+            "\n{} |\n{} | {}\n{} |",
+            gutter, line_number, text, gutter
+        ));
+
+        if let Some(column) = self.column.filter(|&c| c > 0) {
+            // keep tabs so the marker lines up with the source
+            let indent: String = text
+                .chars()
+                .take(column as usize - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            output.push_str(&format!(" {}^", indent));
+        }
+
+        output
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+            if let Some(line) = self.line {
+                write!(f, "{}:", line)?;
+            }
+            if let Some(column) = self.column {
+                write!(f, "{}:", column)?;
+            }
+            write!(f, " ")?;
+        }
+
+        let severity = match self.severity {
+            DiagnosticSeverity::Warning => "warning",
+            DiagnosticSeverity::Error => "error",
+        };
+        write!(f, "{}: {}", severity, self.message)
+    }
+}
+
+/// Formats diagnostics one per line, for error messages.
+pub(super) fn display_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(Diagnostic::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_summary(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    words.next().is_some_and(|n| n.parse::<u32>().is_ok())
+        && matches!(
+            words.next(),
+            Some("error" | "errors" | "warning" | "warnings")
+        )
+        && words.next() == Some("generated.")
+        && words.next().is_none()
+}
+
+fn parse_line(line: &str) -> Option<Diagnostic> {
+    let (location, severity, message) = [
+        ("error", DiagnosticSeverity::Error),
+        ("warning", DiagnosticSeverity::Warning),
+    ]
+    .into_iter()
+    .find_map(|(name, severity)| {
+        if let Some(message) = line.strip_prefix(&format!("{}: ", name)) {
+            return Some(("", severity, message));
+        }
+        let (location, message) = line.split_once(&format!(": {}: ", name))?;
+        Some((location, severity, message))
+    })?;
+
+    // the file name may itself contain colons, so numbers are taken from the end
+    let mut file = location;
+    let mut numbers = Vec::new();
+    while numbers.len() < 2 {
+        match file.rsplit_once(':') {
+            Some((rest, number)) if number.trim().parse::<u32>().is_ok() => {
+                numbers.insert(0, number.trim().parse::<u32>().unwrap());
+                file = rest;
+            }
+            _ => break,
+        }
+    }
+
+    Some(Diagnostic {
+        file: (!file.is_empty()).then(|| file.to_string()),
+        line: numbers.first().copied(),
+        column: numbers.get(1).copied(),
+        severity,
+        message: message.trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let text = "\
+render.glsl:3: error: 'x' : undeclared identifier
+C:\\shaders\\scene.glsl:12:7: warning: '#extension' : extension not supported: GL_EXT_foo
+render.glsl: error: #version: compute shaders require es profile with version 310 or above
+error: failed to find include \"missing.glsl\"
+  while resolving includes
+2 errors generated.
+";
+
+        let diagnostics = Diagnostic::parse(text);
+        assert_eq!(diagnostics.len(), 4);
+
+        assert_eq!(
+            diagnostics[0],
+            Diagnostic {
+                file: Some("render.glsl".to_string()),
+                line: Some(3),
+                column: None,
+                severity: DiagnosticSeverity::Error,
+                message: "'x' : undeclared identifier".to_string(),
+            }
+        );
+
+        assert_eq!(
+            diagnostics[1].file.as_deref(),
+            Some("C:\\shaders\\scene.glsl")
+        );
+        assert_eq!(
+            (diagnostics[1].line, diagnostics[1].column),
+            (Some(12), Some(7))
+        );
+        assert_eq!(diagnostics[1].severity, DiagnosticSeverity::Warning);
+
+        assert_eq!(diagnostics[2].file.as_deref(), Some("render.glsl"));
+        assert_eq!(diagnostics[2].line, None);
+
+        assert_eq!(diagnostics[3].file, None);
+        assert_eq!(
+            diagnostics[3].message,
+            "failed to find include \"missing.glsl\"\nwhile resolving includes"
+        );
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "render.glsl:3: error: 'x' : undeclared identifier"
+        );
+        assert!(Diagnostic::parse("").is_empty());
+    }
+
+    #[test]
+    fn render() {
+        let source = "#version 460\n\nvoid main() { x = 1; }\n";
+        let mut diagnostic = Diagnostic {
+            file: Some("render.glsl".to_string()),
+            line: Some(3),
+            column: None,
+            severity: DiagnosticSeverity::Error,
+            message: "'x' : undeclared identifier".to_string(),
+        };
+
+        assert_eq!(
+            diagnostic.render(Some(source)),
+            "error: 'x' : undeclared identifier\n --> render.glsl:3\n  |\n3 | void main() { x = 1; }\n  |"
+        );
+
+        diagnostic.column = Some(15);
+        assert!(diagnostic
+            .render(Some(source))
+            .ends_with(&format!("\n  | {}^", " ".repeat(14))));
+
+        assert_eq!(
+            diagnostic.render(None),
+            "error: 'x' : undeclared identifier\n --> render.glsl:3:15"
+        );
+
+        diagnostic.line = Some(10);
+        assert_eq!(
+            diagnostic.render(Some(source)),
+            "error: 'x' : undeclared identifier\n --> render.glsl:10:15"
+        );
+    }
+}
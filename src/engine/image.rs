@@ -0,0 +1,388 @@
+use super::*;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ImageError {
+    #[error("requested image size is zero")]
+    SizeIsZero,
+    #[error("the device doesn't support {0:?} images")]
+    UnsupportedFormat(ImageFormat),
+    #[error("the device can't bind {0:?} images as storage images")]
+    StorageNotSupported(ImageFormat),
+    #[error("failed to create vulkan image")]
+    VulkanImageCreationFailed,
+    #[error("failed to create vulkan image view")]
+    VulkanImageViewCreationFailed,
+    #[error("failed to create vulkan sampler")]
+    VulkanSamplerCreationFailed,
+}
+
+/// Texel format of an [`Image`], named after the GLSL image format qualifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// `rgba32f`
+    Rgba32Float,
+    /// `rgba8`, read as floats in [0, 1]
+    Rgba8Unorm,
+    /// `r32f`
+    R32Float,
+    /// `r32ui`
+    R32Uint,
+    /// `r16ui`
+    R16Uint,
+    /// `r8ui`
+    R8Uint,
+}
+
+impl ImageFormat {
+    /// Size of one texel in bytes, which is also its size in a buffer it's copied from or to.
+    pub fn texel_size(self) -> u64 {
+        match self {
+            ImageFormat::Rgba32Float => 16,
+            ImageFormat::Rgba8Unorm | ImageFormat::R32Float | ImageFormat::R32Uint => 4,
+            ImageFormat::R16Uint => 2,
+            ImageFormat::R8Uint => 1,
+        }
+    }
+}
+
+impl From<ImageFormat> for vk::Format {
+    fn from(format: ImageFormat) -> Self {
+        match format {
+            ImageFormat::Rgba32Float => vk::Format::R32G32B32A32_SFLOAT,
+            ImageFormat::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+            ImageFormat::R32Float => vk::Format::R32_SFLOAT,
+            ImageFormat::R32Uint => vk::Format::R32_UINT,
+            ImageFormat::R16Uint => vk::Format::R16_UINT,
+            ImageFormat::R8Uint => vk::Format::R8_UINT,
+        }
+    }
+}
+
+/// A 2D or 3D image in device memory, bound as a storage image (`image2D`, `uimage3D`, ...) or
+/// as a sampled texture (`sampler2D`, `usampler3D`, ...).
+///
+/// Images are filled and read back by copying from and to buffers with
+/// [`TaskBuilder::copy_buffer_to_image`] and [`TaskBuilder::copy_image_to_buffer`]. Texels are
+/// tightly packed in x, y, z order in the buffer.
+#[derive(Clone)]
+pub struct Image {
+    image: Arc<vk::Image>,
+    view: Arc<vk::ImageView>,
+    sampler: Arc<vk::Sampler>,
+    format: ImageFormat,
+    storage: bool,
+}
+
+impl Image {
+    pub fn new_2d(
+        instance: &Instance,
+        format: ImageFormat,
+        size: glam::UVec2,
+    ) -> Result<Image, ImageError> {
+        Self::new(instance, vk::ImageType::Dim2d, format, size.extend(1))
+    }
+
+    pub fn new_3d(
+        instance: &Instance,
+        format: ImageFormat,
+        size: glam::UVec3,
+    ) -> Result<Image, ImageError> {
+        Self::new(instance, vk::ImageType::Dim3d, format, size)
+    }
+
+    fn new(
+        instance: &Instance,
+        image_type: vk::ImageType,
+        format: ImageFormat,
+        size: glam::UVec3,
+    ) -> Result<Image, ImageError> {
+        if size.min_element() == 0 {
+            return Err(ImageError::SizeIsZero);
+        }
+
+        let features = instance
+            .device
+            .physical_device()
+            .format_properties(format.into())
+            .map_err(|_| ImageError::UnsupportedFormat(format))?
+            .optimal_tiling_features;
+
+        // not every format can be written by shaders, those images can still be sampled
+        let storage = features.intersects(vk::FormatFeatures::STORAGE_IMAGE);
+        let mut usage =
+            vk::ImageUsage::SAMPLED | vk::ImageUsage::TRANSFER_SRC | vk::ImageUsage::TRANSFER_DST;
+        if storage {
+            usage |= vk::ImageUsage::STORAGE;
+        }
+        if !features.intersects(vk::FormatFeatures::SAMPLED_IMAGE) {
+            return Err(ImageError::UnsupportedFormat(format));
+        }
+
+        let image = vk::Image::new(
+            instance.memory_allocator.clone(),
+            vk::ImageCreateInfo {
+                image_type,
+                format: format.into(),
+                extent: size.to_array(),
+                usage,
+                ..Default::default()
+            },
+            vk::AllocationCreateInfo {
+                memory_type_filter: vk::MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )
+        .map_err(|_| ImageError::VulkanImageCreationFailed)?;
+
+        let view = vk::ImageView::new_default(image.clone())
+            .map_err(|_| ImageError::VulkanImageViewCreationFailed)?;
+
+        // integer formats can't be filtered, so texels are always fetched with nearest
+        let sampler = vk::Sampler::new(
+            instance.device.clone(),
+            vk::SamplerCreateInfo {
+                mag_filter: vk::Filter::Nearest,
+                min_filter: vk::Filter::Nearest,
+                address_mode: [vk::SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )
+        .map_err(|_| ImageError::VulkanSamplerCreationFailed)?;
+
+        Ok(Image {
+            image,
+            view,
+            sampler,
+            format,
+            storage,
+        })
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// Size in texels, with a depth of 1 for 2D images.
+    pub fn size(&self) -> glam::UVec3 {
+        glam::UVec3::from_array(self.image.extent())
+    }
+
+    pub fn is_3d(&self) -> bool {
+        self.image.image_type() == vk::ImageType::Dim3d
+    }
+
+    /// Whether the device supports the format as a storage image, see [`Image::bind_storage`].
+    pub fn supports_storage(&self) -> bool {
+        self.storage
+    }
+
+    /// Size in bytes of a buffer holding every texel.
+    pub fn byte_len(&self) -> u64 {
+        let size = self.size().as_u64vec3();
+        size.x * size.y * size.z * self.format.texel_size()
+    }
+
+    /// Binds the image for `imageLoad` and `imageStore`. Fails if the device doesn't support
+    /// the format as a storage image, such images can only be sampled.
+    pub fn bind_storage(&self, binding: u32) -> Result<BufferBinding, ImageError> {
+        if !self.storage {
+            return Err(ImageError::StorageNotSupported(self.format));
+        }
+
+        Ok(BufferBinding {
+            binding,
+            kind: DescriptorKind::StorageImage,
+            size: self.byte_len(),
+            write_descriptor_set: vk::WriteDescriptorSet::image_view(binding, self.view.clone()),
+        })
+    }
+
+    /// Binds the image with a nearest, clamp to edge sampler for `texture` and `texelFetch`.
+    pub fn bind_sampled(&self, binding: u32) -> BufferBinding {
+        BufferBinding {
+            binding,
+            kind: DescriptorKind::CombinedImageSampler,
+            size: self.byte_len(),
+            write_descriptor_set: vk::WriteDescriptorSet::image_view_sampler(
+                binding,
+                self.view.clone(),
+                self.sampler.clone(),
+            ),
+        }
+    }
+
+    pub(super) fn get_vk_image(&self) -> &Arc<vk::Image> {
+        &self.image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creation() {
+        let instance = Instance::new().unwrap();
+
+        let image = Image::new_2d(&instance, ImageFormat::Rgba32Float, glam::uvec2(8, 4)).unwrap();
+        assert_eq!(image.size(), glam::uvec3(8, 4, 1));
+        assert_eq!(image.byte_len(), 8 * 4 * 16);
+        assert!(!image.is_3d());
+
+        let image = Image::new_3d(&instance, ImageFormat::R16Uint, glam::uvec3(4, 4, 4)).unwrap();
+        assert_eq!(image.byte_len(), 4 * 4 * 4 * 2);
+        assert!(image.is_3d());
+
+        assert!(matches!(
+            Image::new_3d(&instance, ImageFormat::R8Uint, glam::uvec3(4, 0, 4)),
+            Err(ImageError::SizeIsZero)
+        ));
+    }
+
+    #[test]
+    fn storage_support() {
+        let instance = Instance::new().unwrap();
+        let formats = [
+            ImageFormat::Rgba32Float,
+            ImageFormat::Rgba8Unorm,
+            ImageFormat::R32Float,
+            ImageFormat::R32Uint,
+            ImageFormat::R16Uint,
+            ImageFormat::R8Uint,
+        ];
+
+        for format in formats {
+            let Ok(image) = Image::new_2d(&instance, format, glam::uvec2(2, 2)) else {
+                continue;
+            };
+            let storage = instance
+                .device
+                .physical_device()
+                .format_properties(format.into())
+                .unwrap()
+                .optimal_tiling_features
+                .intersects(vk::FormatFeatures::STORAGE_IMAGE);
+
+            assert_eq!(image.supports_storage(), storage);
+            match image.bind_storage(0) {
+                Ok(binding) => assert!(storage && binding.kind() == DescriptorKind::StorageImage),
+                Err(ImageError::StorageNotSupported(f)) => assert!(!storage && f == format),
+                Err(e) => panic!("unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn copy() {
+        let instance = Instance::new().unwrap();
+        let image = Image::new_3d(&instance, ImageFormat::R32Uint, glam::uvec3(2, 2, 2)).unwrap();
+        let src = CpuBuffer::from_vec(&instance, (0u32..8).collect()).unwrap();
+        let dst = CpuBuffer::<u32>::new(&instance, 8).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .copy_buffer_to_image(&src, &image)
+            .unwrap()
+            .copy_image_to_buffer(&image, &dst)
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        assert_eq!(dst.read().unwrap(), (0..8).collect::<Vec<_>>());
+
+        assert!(matches!(
+            TaskBuilder::new(&instance)
+                .unwrap()
+                .copy_buffer_to_image(&src.sub(0..4).unwrap(), &image),
+            Err(TaskError::ImageCopySizeMismatch {
+                image: 32,
+                buffer: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn storage_image() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0, rgba32f) uniform writeonly image2D image;
+            void main() {
+                ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+                imageStore(image, pos, vec4(pos, 0.0, 1.0));
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, code, "test.glsl", "main").unwrap();
+        let image = Image::new_2d(&instance, ImageFormat::Rgba32Float, glam::uvec2(3, 2)).unwrap();
+        let buffer = CpuBuffer::<glam::Vec4>::new(&instance, 6).unwrap();
+
+        assert!(matches!(
+            TaskBuilder::new(&instance).unwrap().run_program(
+                &program,
+                (3, 2, 1),
+                vec![image.bind_sampled(0)]
+            ),
+            Err(TaskError::BindingKindMismatch {
+                expected: DescriptorKind::StorageImage,
+                found: DescriptorKind::CombinedImageSampler,
+                ..
+            })
+        ));
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(&program, (3, 2, 1), vec![image.bind_storage(0).unwrap()])
+            .unwrap()
+            .copy_image_to_buffer(&image, &buffer)
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        let texels = buffer.read().unwrap();
+        assert_eq!(texels[0], glam::vec4(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(texels[5], glam::vec4(2.0, 1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn sampled_3d_texture() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) uniform usampler3D voxels;
+            layout(binding = 1) buffer Data { uint data[]; };
+            void main() {
+                ivec3 pos = ivec3(gl_GlobalInvocationID);
+                uint idx = pos.z * 16 + pos.y * 4 + pos.x;
+                data[idx] = texelFetch(voxels, pos, 0).r * 2;
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, code, "test.glsl", "main").unwrap();
+        let voxels = Image::new_3d(&instance, ImageFormat::R8Uint, glam::uvec3(4, 4, 4)).unwrap();
+        let upload = CpuBuffer::from_vec(&instance, (0u8..64).collect()).unwrap();
+        let data = CpuBuffer::<u32>::new(&instance, 64).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .copy_buffer_to_image(&upload, &voxels)
+            .unwrap()
+            .run_program(
+                &program,
+                (4, 4, 4),
+                vec![voxels.bind_sampled(0), data.bind(1)],
+            )
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        assert_eq!(
+            data.read().unwrap(),
+            (0..64).map(|i| i * 2).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use shaderc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Headers in `shader/` that are embedded in the binary, see [`ShaderIncludes::builtin`].
+const BUILTIN_FILES: &[(&str, &str)] = &[
+    ("camera.glsl", include_str!("../../shader/camera.glsl")),
+    ("dda.glsl", include_str!("../../shader/dda.glsl")),
+    ("image.glsl", include_str!("../../shader/image.glsl")),
+    ("octree.glsl", include_str!("../../shader/octree.glsl")),
+    (
+        "path_trace.glsl",
+        include_str!("../../shader/path_trace.glsl"),
+    ),
+    ("random.glsl", include_str!("../../shader/random.glsl")),
+    ("ray.glsl", include_str!("../../shader/ray.glsl")),
+    ("scene.glsl", include_str!("../../shader/scene.glsl")),
+    ("voxel.glsl", include_str!("../../shader/voxel.glsl")),
+];
+
+/// Resolves `#include` directives while compiling a [`Program`](super::Program).
+///
+/// `#include "file"` is first looked up next to the including file if that file is on disk,
+/// `#include <file>` skips that step. Both then try each search path in order and finally the
+/// virtual files, so a header on disk can override a built-in one.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderIncludes {
+    search_paths: Vec<PathBuf>,
+    files: HashMap<String, String>,
+}
+
+impl ShaderIncludes {
+    /// Creates a resolver without any search paths or virtual files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a resolver with the built-in shader library as virtual files: `ray.glsl`,
+    /// `camera.glsl`, `scene.glsl`, `image.glsl`, `random.glsl`, the `dda.glsl` and
+    /// `octree.glsl` traversals and the `voxel.glsl` and `path_trace.glsl` render passes.
+    pub fn builtin() -> Self {
+        BUILTIN_FILES
+            .iter()
+            .fold(Self::new(), |includes, (name, source)| {
+                includes.file(*name, *source)
+            })
+    }
+
+    pub fn search_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+
+    /// Adds a virtual file, replacing any previous file with the same name.
+    pub fn file(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.files.insert(name.into(), source.into());
+        self
+    }
+
+    /// Source of the virtual file `name`.
+    pub fn source(&self, name: &str) -> Option<&str> {
+        self.files.get(name).map(String::as_str)
+    }
+
+    /// Whether `resolved_name` refers to a virtual file rather than one on disk.
+    pub(super) fn is_virtual(&self, resolved_name: &str) -> bool {
+        self.files.contains_key(resolved_name)
+    }
+
+    pub(super) fn resolve(
+        &self,
+        requested: &str,
+        include_type: shaderc::IncludeType,
+        requesting: &str,
+    ) -> shaderc::IncludeCallbackResult {
+        let sibling = match include_type {
+            shaderc::IncludeType::Relative if Path::new(requesting).is_file() => {
+                Path::new(requesting)
+                    .parent()
+                    .map(|dir| dir.join(requested))
+            }
+            _ => None,
+        };
+
+        let on_disk = sibling
+            .into_iter()
+            .chain(self.search_paths.iter().map(|dir| dir.join(requested)))
+            .find(|path| path.is_file());
+
+        if let Some(path) = on_disk {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read \"{}\": {}", path.display(), e))?;
+            return Ok(shaderc::ResolvedInclude {
+                resolved_name: path.display().to_string(),
+                content,
+            });
+        }
+
+        self.files
+            .get(requested)
+            .map(|content| shaderc::ResolvedInclude {
+                resolved_name: requested.to_string(),
+                content: content.clone(),
+            })
+            .ok_or_else(|| format!("failed to find include \"{}\"", requested))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::*;
+
+    #[test]
+    fn resolve() {
+        let dir = std::env::temp_dir().join("voxel_renderer_shader_includes");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib/camera.glsl"), "// camera override").unwrap();
+        std::fs::write(dir.join("lib/sibling.glsl"), "// sibling").unwrap();
+
+        let includes = ShaderIncludes::builtin().search_path(dir.join("lib"));
+        let requesting = dir.join("lib/camera.glsl").display().to_string();
+
+        let ray = includes
+            .resolve("ray.glsl", shaderc::IncludeType::Relative, "camera.glsl")
+            .unwrap();
+        assert_eq!(ray.resolved_name, "ray.glsl");
+        assert!(ray.content.contains("struct Ray"));
+
+        let camera = includes
+            .resolve("camera.glsl", shaderc::IncludeType::Standard, "main.glsl")
+            .unwrap();
+        assert_eq!(camera.content, "// camera override");
+
+        let sibling = includes
+            .resolve("sibling.glsl", shaderc::IncludeType::Relative, &requesting)
+            .unwrap();
+        assert_eq!(sibling.content, "// sibling");
+
+        assert!(ShaderIncludes::new()
+            .resolve("ray.glsl", shaderc::IncludeType::Standard, "main.glsl")
+            .is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn compile_with_includes() {
+        let code = r#"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            #include "image.glsl"
+            #include <scale.glsl>
+            void main() { image_store(vec4(vec2(image_pos()) * SCALE, 0.0, 1.0)); }
+        "#;
+
+        let instance = Instance::new().unwrap();
+        let includes = ShaderIncludes::builtin().file("scale.glsl", "const float SCALE = 2.0;");
+        let program =
+            Program::with_includes(&instance, code, "test.glsl", "main", &includes).unwrap();
+        let image = CpuBuffer::<glam::Vec4>::new(&instance, 4).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(&program, (2, 2, 1), vec![image.bind(0)])
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        assert_eq!(
+            image.read().unwrap(),
+            [
+                glam::vec4(0.0, 0.0, 0.0, 1.0),
+                glam::vec4(2.0, 0.0, 0.0, 1.0),
+                glam::vec4(0.0, 2.0, 0.0, 1.0),
+                glam::vec4(2.0, 2.0, 0.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_include() {
+        let code = r#"
+            #version 460
+            #include "missing.glsl"
+            void main() {}
+        "#;
+
+        let instance = Instance::new().unwrap();
+        let error = Program::new(&instance, code, "test.glsl", "main")
+            .err()
+            .unwrap();
+        match error {
+            ProgramError::CompilationFailed(diagnostics) => assert!(diagnostics.iter().any(|d| d
+                .file
+                .as_deref()
+                == Some("test.glsl")
+                && d.message.contains("missing.glsl"))),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
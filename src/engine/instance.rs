@@ -1,4 +1,7 @@
+use super::cache::ShaderCache;
+use super::debug::{DebugCallback, DebugMessenger, VALIDATION_LAYER};
 use super::*;
+use std::path::PathBuf;
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -14,38 +17,277 @@ pub enum InstanceError {
     NoVulkanQueue,
     #[error("failed to find create vulkan device")]
     VulkanDeviceCreationFailed,
+    #[error("no vulkan device matches the selection, available devices: {0}")]
+    NoMatchingDevice(String),
+    #[error("failed to create vulkan debug messenger")]
+    DebugMessengerCreationFailed,
+    #[error("failed to create shader cache in \"{0}\"")]
+    CacheCreationFailed(String),
+    #[error("failed to save vulkan pipeline cache")]
+    PipelineCacheSaveFailed,
 }
 
-pub struct Instance {
-    instance: Arc<vk::Instance>,
-    pub(super) device: Arc<vk::Device>,
-    pub(super) queue: Arc<vk::Queue>,
-    pub(super) queue_family_index: u32,
-    pub(super) memory_allocator: Arc<vk::StandardMemoryAllocator>,
-    pub(super) command_buffer_allocator: Arc<vk::StandardCommandBufferAllocator>,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceType {
+    DiscreteGpu,
+    IntegratedGpu,
+    VirtualGpu,
+    Cpu,
+    Other,
 }
 
-impl Instance {
-    pub fn new() -> Result<Self, InstanceError> {
-        let library = vk::VulkanLibrary::new().map_err(|_| InstanceError::NoVulkanLibrary)?;
-        let instance = vk::Instance::new(library, vk::InstanceCreateInfo::default())
-            .map_err(|_| InstanceError::VulkanInstanceCreationFailed)?;
+impl From<vk::PhysicalDeviceType> for DeviceType {
+    fn from(device_type: vk::PhysicalDeviceType) -> Self {
+        match device_type {
+            vk::PhysicalDeviceType::DiscreteGpu => DeviceType::DiscreteGpu,
+            vk::PhysicalDeviceType::IntegratedGpu => DeviceType::IntegratedGpu,
+            vk::PhysicalDeviceType::VirtualGpu => DeviceType::VirtualGpu,
+            vk::PhysicalDeviceType::Cpu => DeviceType::Cpu,
+            _ => DeviceType::Other,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryHeap {
+    /// Size in bytes.
+    pub size: u64,
+    /// Whether the heap is local to the device, which is where [`GpuBuffer`]s are allocated.
+    pub device_local: bool,
+}
 
-        let physical_device = instance
-            .enumerate_physical_devices()
-            .map_err(|_| InstanceError::NoVulkanDevice)?
-            .next()
-            .ok_or(InstanceError::NoVulkanDevice)?;
+/// Subgroup operations available to compute shaders, all false if the device doesn't support
+/// subgroups in the compute stage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SubgroupOperations {
+    pub basic: bool,
+    pub vote: bool,
+    pub arithmetic: bool,
+    pub ballot: bool,
+    pub shuffle: bool,
+    pub shuffle_relative: bool,
+    pub clustered: bool,
+    pub quad: bool,
+}
+
+/// Description of a physical device, as returned by [`InstanceBuilder::enumerate_devices`] and
+/// [`Instance::device_info`].
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    /// Position in the list of enumerated devices, see [`InstanceBuilder::device_index`].
+    pub index: usize,
+    pub name: String,
+    pub device_type: DeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub api_version: Version,
+    /// Vendor specific encoding of the driver version.
+    pub driver_version: u32,
+    pub driver_name: Option<String>,
+    pub driver_info: Option<String>,
+    pub max_compute_work_group_count: [u32; 3],
+    pub max_compute_work_group_size: [u32; 3],
+    pub max_compute_work_group_invocations: u32,
+    pub max_compute_shared_memory_size: u32,
+    /// Largest range in bytes a single storage buffer binding may cover.
+    pub max_storage_buffer_range: u32,
+    pub max_uniform_buffer_range: u32,
+    pub max_push_constants_size: u32,
+    pub memory_heaps: Vec<MemoryHeap>,
+    pub subgroup_size: Option<u32>,
+    pub subgroup_operations: SubgroupOperations,
+    /// Whether the device has a queue family that supports compute, which [`Instance`] needs.
+    pub supports_compute: bool,
+}
 
-        let queue_family_index = physical_device
-            .queue_family_properties()
+impl DeviceInfo {
+    fn new(index: usize, physical_device: &vk::PhysicalDevice) -> Self {
+        let properties = physical_device.properties();
+
+        let memory_heaps = physical_device
+            .memory_properties()
+            .memory_heaps
             .iter()
-            .position(|queue_family_properties| {
-                queue_family_properties
-                    .queue_flags
-                    .contains(vk::QueueFlags::COMPUTE)
+            .map(|heap| MemoryHeap {
+                size: heap.size,
+                device_local: heap.flags.intersects(vk::MemoryHeapFlags::DEVICE_LOCAL),
             })
-            .ok_or(InstanceError::NoVulkanQueue)? as u32;
+            .collect();
+
+        let compute_subgroups = properties
+            .subgroup_supported_stages
+            .is_some_and(|stages| stages.intersects(vk::ShaderStages::COMPUTE));
+        let subgroup_operations = match properties.subgroup_supported_operations {
+            Some(operations) if compute_subgroups => SubgroupOperations {
+                basic: operations.intersects(vk::SubgroupFeatures::BASIC),
+                vote: operations.intersects(vk::SubgroupFeatures::VOTE),
+                arithmetic: operations.intersects(vk::SubgroupFeatures::ARITHMETIC),
+                ballot: operations.intersects(vk::SubgroupFeatures::BALLOT),
+                shuffle: operations.intersects(vk::SubgroupFeatures::SHUFFLE),
+                shuffle_relative: operations.intersects(vk::SubgroupFeatures::SHUFFLE_RELATIVE),
+                clustered: operations.intersects(vk::SubgroupFeatures::CLUSTERED),
+                quad: operations.intersects(vk::SubgroupFeatures::QUAD),
+            },
+            _ => SubgroupOperations::default(),
+        };
+
+        DeviceInfo {
+            index,
+            name: properties.device_name.clone(),
+            device_type: properties.device_type.into(),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            api_version: properties.api_version.into(),
+            driver_version: properties.driver_version,
+            driver_name: properties.driver_name.clone(),
+            driver_info: properties.driver_info.clone(),
+            max_compute_work_group_count: properties.max_compute_work_group_count,
+            max_compute_work_group_size: properties.max_compute_work_group_size,
+            max_compute_work_group_invocations: properties.max_compute_work_group_invocations,
+            max_compute_shared_memory_size: properties.max_compute_shared_memory_size,
+            max_storage_buffer_range: properties.max_storage_buffer_range,
+            max_uniform_buffer_range: properties.max_uniform_buffer_range,
+            max_push_constants_size: properties.max_push_constants_size,
+            memory_heaps,
+            subgroup_size: properties.subgroup_size,
+            subgroup_operations,
+            supports_compute: compute_queue_family_index(physical_device).is_some(),
+        }
+    }
+
+    /// Total size in bytes of the device local heaps.
+    pub fn device_local_memory(&self) -> u64 {
+        self.memory_heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(|heap| heap.size)
+            .sum()
+    }
+}
+
+/// Selects the physical device an [`Instance`] runs on.
+///
+/// Devices are first narrowed down by every criterion that was set, then the remaining ones are
+/// ranked by device type. Without an explicit preference discrete GPUs come first, followed by
+/// integrated, virtual and CPU implementations.
+pub struct InstanceBuilder {
+    device_index: Option<usize>,
+    device_name: Option<String>,
+    device_types: Vec<DeviceType>,
+    device_filter: Option<Box<dyn Fn(&DeviceInfo) -> bool>>,
+    debug: bool,
+    debug_severity: DebugSeverity,
+    debug_callback: Option<DebugCallback>,
+    cache_directory: Option<PathBuf>,
+}
+
+impl InstanceBuilder {
+    pub fn new() -> Self {
+        Self {
+            device_index: None,
+            device_name: None,
+            device_types: vec![
+                DeviceType::DiscreteGpu,
+                DeviceType::IntegratedGpu,
+                DeviceType::VirtualGpu,
+                DeviceType::Cpu,
+            ],
+            device_filter: None,
+            debug: false,
+            debug_severity: DebugSeverity::Warning,
+            debug_callback: None,
+            cache_directory: None,
+        }
+    }
+
+    /// Only allows the device at `index` in [`InstanceBuilder::enumerate_devices`].
+    pub fn device_index(mut self, index: usize) -> Self {
+        self.device_index = Some(index);
+        self
+    }
+
+    /// Only allows devices whose name contains `name`, ignoring case.
+    pub fn device_name(mut self, name: &str) -> Self {
+        self.device_name = Some(name.to_lowercase());
+        self
+    }
+
+    /// Ranks devices by the position of their type in `device_types`, unlisted types last.
+    pub fn prefer_device_types(mut self, device_types: &[DeviceType]) -> Self {
+        self.device_types = device_types.to_vec();
+        self
+    }
+
+    /// Only allows devices for which `filter` returns true.
+    pub fn device_filter(mut self, filter: impl Fn(&DeviceInfo) -> bool + 'static) -> Self {
+        self.device_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Enables `VK_LAYER_KHRONOS_validation` when it is installed and collects debug utils
+    /// messages, see [`Instance::take_debug_messages`].
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Drops debug messages below `severity`, [`DebugSeverity::Warning`] by default.
+    pub fn debug_severity(mut self, severity: DebugSeverity) -> Self {
+        self.debug_severity = severity;
+        self
+    }
+
+    /// Calls `callback` for every debug message as it is reported, in addition to collecting it.
+    /// The callback may run on any thread and must not call into the engine.
+    pub fn debug_callback(
+        mut self,
+        callback: impl Fn(&DebugMessage) + Send + Sync + 'static,
+    ) -> Self {
+        self.debug_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Caches compiled SPIR-V and the vulkan pipeline cache in `directory`, creating it if
+    /// needed, so later runs skip shader compilation. Nothing is cached by default.
+    pub fn cache_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.cache_directory = Some(directory.into());
+        self
+    }
+
+    /// Disables caching, undoing [`InstanceBuilder::cache_directory`].
+    pub fn no_cache(mut self) -> Self {
+        self.cache_directory = None;
+        self
+    }
+
+    pub fn enumerate_devices(&self) -> Result<Vec<DeviceInfo>, InstanceError> {
+        let (instance, _) = self.create_vk_instance()?;
+        Ok(enumerate_physical_devices(&instance)?
+            .iter()
+            .enumerate()
+            .map(|(index, physical_device)| DeviceInfo::new(index, physical_device))
+            .collect())
+    }
+
+    pub fn build(self) -> Result<Instance, InstanceError> {
+        let (instance, validation_enabled) = self.create_vk_instance()?;
+
+        let debug_messenger = if self.debug && instance.enabled_extensions().ext_debug_utils {
+            Some(Arc::new(DebugMessenger::new(
+                &instance,
+                self.debug_severity,
+                self.debug_callback.clone(),
+            )?))
+        } else {
+            None
+        };
+
+        let physical_devices = enumerate_physical_devices(&instance)?;
+        let device_info = self.select_device(&physical_devices)?;
+        let physical_device = physical_devices[device_info.index].clone();
+
+        let queue_family_index =
+            compute_queue_family_index(&physical_device).ok_or(InstanceError::NoVulkanQueue)?;
 
         let (device, mut queues) = vk::Device::new(
             physical_device,
@@ -59,7 +301,16 @@ impl Instance {
         )
         .map_err(|_| InstanceError::VulkanDeviceCreationFailed)?;
 
-        Ok(Self {
+        let shader_cache = match &self.cache_directory {
+            Some(directory) => Some(Arc::new(ShaderCache::new(
+                &device,
+                &device_info,
+                directory.clone(),
+            )?)),
+            None => None,
+        };
+
+        Ok(Instance {
             instance: instance.clone(),
             device: device.clone(),
             queue: queues.next().unwrap(),
@@ -69,24 +320,219 @@ impl Instance {
                 device.clone(),
                 vk::StandardCommandBufferAllocatorCreateInfo::default(),
             )),
+            device_info: Arc::new(device_info),
+            debug_messenger,
+            validation_enabled,
+            shader_cache,
         })
     }
 
-    pub fn api_version(&self) -> Version {
-        Version {
-            major: self.instance.api_version().major,
-            minor: self.instance.api_version().minor,
-            patch: self.instance.api_version().patch,
+    /// Creates the vulkan instance, returning whether the validation layer was enabled.
+    fn create_vk_instance(&self) -> Result<(Arc<vk::Instance>, bool), InstanceError> {
+        let library = vk::VulkanLibrary::new().map_err(|_| InstanceError::NoVulkanLibrary)?;
+
+        if !self.debug {
+            let instance = vk::Instance::new(library, vk::InstanceCreateInfo::default())
+                .map_err(|_| InstanceError::VulkanInstanceCreationFailed)?;
+            return Ok((instance, false));
         }
+
+        let validation_available = library
+            .layer_properties()
+            .map_err(|_| InstanceError::VulkanInstanceCreationFailed)?
+            .any(|layer| layer.name() == VALIDATION_LAYER);
+
+        let enabled_layers: Vec<String> = validation_available
+            .then(|| VALIDATION_LAYER.to_string())
+            .into_iter()
+            .collect();
+
+        let ext_debug_utils = library
+            .supported_extensions_with_layers(enabled_layers.iter().map(String::as_str))
+            .map_err(|_| InstanceError::VulkanInstanceCreationFailed)?
+            .ext_debug_utils;
+
+        let instance = vk::Instance::new(
+            library,
+            vk::InstanceCreateInfo {
+                enabled_layers,
+                enabled_extensions: vk::InstanceExtensions {
+                    ext_debug_utils,
+                    ..vk::InstanceExtensions::empty()
+                },
+                ..Default::default()
+            },
+        )
+        .map_err(|_| InstanceError::VulkanInstanceCreationFailed)?;
+
+        Ok((instance, validation_available))
+    }
+
+    fn select_device(
+        &self,
+        physical_devices: &[Arc<vk::PhysicalDevice>],
+    ) -> Result<DeviceInfo, InstanceError> {
+        let devices: Vec<DeviceInfo> = physical_devices
+            .iter()
+            .enumerate()
+            .map(|(index, physical_device)| DeviceInfo::new(index, physical_device))
+            .collect();
+
+        let rank = |device: &DeviceInfo| {
+            self.device_types
+                .iter()
+                .position(|&device_type| device_type == device.device_type)
+                .unwrap_or(self.device_types.len())
+        };
+
+        devices
+            .iter()
+            .filter(|device| device.supports_compute && self.matches(device))
+            .min_by_key(|device| rank(device))
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<String> = devices
+                    .iter()
+                    .map(|device| format!("{} \"{}\"", device.index, device.name))
+                    .collect();
+                InstanceError::NoMatchingDevice(names.join(", "))
+            })
     }
+
+    fn matches(&self, device: &DeviceInfo) -> bool {
+        self.device_index.is_none_or(|index| index == device.index)
+            && self
+                .device_name
+                .as_ref()
+                .is_none_or(|name| device.name.to_lowercase().contains(name))
+            && self
+                .device_filter
+                .as_ref()
+                .is_none_or(|filter| filter(device))
+    }
+}
+
+impl Default for InstanceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+pub struct Instance {
+    instance: Arc<vk::Instance>,
+    pub(super) device: Arc<vk::Device>,
+    pub(super) queue: Arc<vk::Queue>,
+    pub(super) queue_family_index: u32,
+    pub(super) memory_allocator: Arc<vk::StandardMemoryAllocator>,
+    pub(super) command_buffer_allocator: Arc<vk::StandardCommandBufferAllocator>,
+    device_info: Arc<DeviceInfo>,
+    debug_messenger: Option<Arc<DebugMessenger>>,
+    validation_enabled: bool,
+    pub(super) shader_cache: Option<Arc<ShaderCache>>,
 }
 
+impl Instance {
+    /// Creates an instance on the preferred device, see [`InstanceBuilder`].
+    pub fn new() -> Result<Self, InstanceError> {
+        InstanceBuilder::new().build()
+    }
+
+    /// The physical device the instance runs on.
+    pub fn device_info(&self) -> &DeviceInfo {
+        &self.device_info
+    }
+
+    /// Whether the instance was built in debug mode with the validation layer available.
+    pub fn validation_enabled(&self) -> bool {
+        self.validation_enabled
+    }
+
+    /// Whether both instances run on the same logical device, which buffers and programs of one
+    /// need to be used with the other. Clones of an instance share its device.
+    pub fn shares_device(&self, other: &Instance) -> bool {
+        Arc::ptr_eq(&self.device, &other.device)
+    }
+
+    /// Debug messages collected so far, empty unless built with [`InstanceBuilder::debug`].
+    pub fn debug_messages(&self) -> Vec<DebugMessage> {
+        self.debug_messenger
+            .as_ref()
+            .map(|messenger| messenger.messages())
+            .unwrap_or_default()
+    }
+
+    /// Like [`Instance::debug_messages`], but clears the collected messages.
+    pub fn take_debug_messages(&self) -> Vec<DebugMessage> {
+        self.debug_messenger
+            .as_ref()
+            .map(|messenger| messenger.take_messages())
+            .unwrap_or_default()
+    }
+
+    /// Shader cache statistics, or `None` without a [`InstanceBuilder::cache_directory`].
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.shader_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Writes the pipeline cache to the cache directory now instead of when the instance is
+    /// dropped. Does nothing without a cache directory.
+    pub fn save_pipeline_cache(&self) -> Result<(), InstanceError> {
+        match &self.shader_cache {
+            Some(cache) => cache.save_pipeline_cache(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn api_version(&self) -> Version {
+        self.instance.api_version().into()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Version {
     pub major: u32,
     pub minor: u32,
     pub patch: u32,
 }
 
+impl From<vk::Version> for Version {
+    fn from(version: vk::Version) -> Self {
+        Version {
+            major: version.major,
+            minor: version.minor,
+            patch: version.patch,
+        }
+    }
+}
+
+fn enumerate_physical_devices(
+    instance: &Arc<vk::Instance>,
+) -> Result<Vec<Arc<vk::PhysicalDevice>>, InstanceError> {
+    let physical_devices: Vec<_> = instance
+        .enumerate_physical_devices()
+        .map_err(|_| InstanceError::NoVulkanDevice)?
+        .collect();
+
+    if physical_devices.is_empty() {
+        return Err(InstanceError::NoVulkanDevice);
+    }
+
+    Ok(physical_devices)
+}
+
+fn compute_queue_family_index(physical_device: &vk::PhysicalDevice) -> Option<u32> {
+    physical_device
+        .queue_family_properties()
+        .iter()
+        .position(|queue_family_properties| {
+            queue_family_properties
+                .queue_flags
+                .contains(vk::QueueFlags::COMPUTE)
+        })
+        .map(|index| index as u32)
+}
+
 impl std::fmt::Display for Version {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
@@ -102,6 +548,13 @@ mod tests {
         assert!(Instance::new().is_ok());
     }
 
+    #[test]
+    fn shares_device() {
+        let instance = Instance::new().unwrap();
+        assert!(instance.shares_device(&instance.clone()));
+        assert!(!instance.shares_device(&Instance::new().unwrap()));
+    }
+
     #[test]
     fn version() {
         let instance = Instance::new().unwrap();
@@ -111,4 +564,77 @@ mod tests {
         assert!(instance.api_version().minor <= library.api_version().minor);
         assert!(instance.api_version().patch <= library.api_version().patch);
     }
+
+    #[test]
+    fn enumerate_devices() {
+        let devices = InstanceBuilder::new().enumerate_devices().unwrap();
+
+        assert!(!devices.is_empty());
+        for (index, device) in devices.iter().enumerate() {
+            assert_eq!(device.index, index);
+            assert!(!device.name.is_empty());
+            assert!(device.max_compute_work_group_invocations >= 128);
+            assert!(device
+                .max_compute_work_group_count
+                .iter()
+                .all(|&c| c >= 65535));
+        }
+    }
+
+    #[test]
+    fn device_info() {
+        let instance = Instance::new().unwrap();
+        let info = instance.device_info();
+
+        assert!(info.supports_compute);
+        assert_eq!(
+            info.name,
+            InstanceBuilder::new().enumerate_devices().unwrap()[info.index].name
+        );
+        assert!(info.max_storage_buffer_range >= 1 << 27);
+        assert!(info.max_push_constants_size >= 128);
+        assert!(info.device_local_memory() > 0);
+
+        if info.subgroup_operations != SubgroupOperations::default() {
+            assert!(info.subgroup_operations.basic);
+            assert!(info.subgroup_size.is_some());
+        }
+    }
+
+    #[test]
+    fn select_device() {
+        let devices = InstanceBuilder::new().enumerate_devices().unwrap();
+        let device = devices
+            .iter()
+            .find(|device| device.supports_compute)
+            .unwrap();
+
+        assert!(InstanceBuilder::new()
+            .device_index(device.index)
+            .build()
+            .is_ok());
+        assert!(InstanceBuilder::new()
+            .device_name(&device.name.to_uppercase())
+            .prefer_device_types(&[])
+            .build()
+            .is_ok());
+
+        let device_type = device.device_type;
+        assert!(InstanceBuilder::new()
+            .device_filter(move |device| device.device_type == device_type)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn no_matching_device() {
+        let result = InstanceBuilder::new().device_index(usize::MAX).build();
+        assert!(matches!(result, Err(InstanceError::NoMatchingDevice(_))));
+
+        let result = InstanceBuilder::new().device_name("no such device").build();
+        assert!(matches!(result, Err(InstanceError::NoMatchingDevice(_))));
+
+        let result = InstanceBuilder::new().device_filter(|_| false).build();
+        assert!(matches!(result, Err(InstanceError::NoMatchingDevice(_))));
+    }
 }
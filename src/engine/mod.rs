@@ -1,13 +1,37 @@
 mod buffer;
+mod buffer_object;
+mod cache;
+mod debug;
+mod diagnostics;
+mod image;
+mod includes;
 mod instance;
 mod program;
+mod reflection;
 mod task;
+mod uniform_buffer;
 mod vulkan;
+mod watcher;
 
 pub use buffer::{
     buffer_location, Buffer, BufferBinding, BufferContents, BufferError, CpuBuffer, GpuBuffer,
 };
-pub use instance::{Instance, InstanceError, Version};
-pub use program::{Program, ProgramError};
+pub use buffer_object::{buffer_object_state, BufferObject, BufferObjectError};
+pub use cache::CacheStats;
+pub use debug::{DebugMessage, DebugSeverity};
+pub use diagnostics::{Diagnostic, DiagnosticSeverity};
+pub use image::{Image, ImageError, ImageFormat};
+pub use includes::ShaderIncludes;
+pub use instance::{
+    DeviceInfo, DeviceType, Instance, InstanceBuilder, InstanceError, MemoryHeap,
+    SubgroupOperations, Version,
+};
+pub use program::{
+    spirv_from_bytes, spirv_to_bytes, OptimizationLevel, Program, ProgramBuilder, ProgramError,
+    TargetEnvironment,
+};
+pub use reflection::{BindingInfo, DescriptorKind, Reflection, ReflectionError};
 pub use task::{Task, TaskBuilder, TaskError, TaskFuture};
+pub use uniform_buffer::UniformBuffer;
 use vulkan as vk;
+pub use watcher::ProgramWatcher;
@@ -1,12 +1,19 @@
+use super::cache::{Dependency, Fnv1a};
+use super::diagnostics::display_diagnostics;
 use super::*;
 use shaderc;
+use std::cell::RefCell;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum ProgramError {
-    #[error("failed to compile shader: {0}")]
-    CompilationFailed(String),
+    #[error("failed to compile shader:\n{}", display_diagnostics(.0))]
+    CompilationFailed(Vec<Diagnostic>),
+    #[error("shader compiler failed: {0}")]
+    CompilerFailed(String),
     #[error("failed to create vulkan shader module")]
     VulkanShaderModuleCreationFailed,
     #[error("failed to find entry point \"{0}\"")]
@@ -15,41 +22,418 @@ pub enum ProgramError {
     VulkanPipelineLayoutCreationFailed,
     #[error("failed to create vulkan pipeline")]
     VulkanPipelineCreationFailed,
+    #[error("failed to reflect shader: {0}")]
+    ReflectionFailed(#[from] ReflectionError),
+    #[error("target environment {0:?} is newer than the device supports")]
+    UnsupportedTargetEnvironment(TargetEnvironment),
+    #[error("failed to read SPIR-V file \"{0}\"")]
+    SpirvReadFailed(String),
+    #[error("invalid SPIR-V module")]
+    InvalidSpirv,
+    #[error("failed to read shader source \"{0}\"")]
+    SourceReadFailed(String),
 }
 
+/// Optimization passes run on the compiled SPIR-V.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OptimizationLevel {
+    #[default]
+    Zero,
+    Size,
+    Performance,
+}
+
+impl From<OptimizationLevel> for shaderc::OptimizationLevel {
+    fn from(level: OptimizationLevel) -> Self {
+        match level {
+            OptimizationLevel::Zero => shaderc::OptimizationLevel::Zero,
+            OptimizationLevel::Size => shaderc::OptimizationLevel::Size,
+            OptimizationLevel::Performance => shaderc::OptimizationLevel::Performance,
+        }
+    }
+}
+
+/// Vulkan version the SPIR-V is generated for, which also selects the SPIR-V version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TargetEnvironment {
+    #[default]
+    Vulkan1_0,
+    Vulkan1_1,
+    Vulkan1_2,
+}
+
+impl TargetEnvironment {
+    fn version(self) -> Version {
+        let minor = match self {
+            TargetEnvironment::Vulkan1_0 => 0,
+            TargetEnvironment::Vulkan1_1 => 1,
+            TargetEnvironment::Vulkan1_2 => 2,
+        };
+
+        Version {
+            major: 1,
+            minor,
+            patch: 0,
+        }
+    }
+}
+
+impl From<TargetEnvironment> for shaderc::EnvVersion {
+    fn from(target: TargetEnvironment) -> Self {
+        match target {
+            TargetEnvironment::Vulkan1_0 => shaderc::EnvVersion::Vulkan1_0,
+            TargetEnvironment::Vulkan1_1 => shaderc::EnvVersion::Vulkan1_1,
+            TargetEnvironment::Vulkan1_2 => shaderc::EnvVersion::Vulkan1_2,
+        }
+    }
+}
+
+/// Compiles GLSL into a [`Program`] with preprocessor definitions and compiler options.
+///
+/// The builder can be cloned and built again with different definitions to create variants of
+/// the same shader, such as the path tracer with a different `MAX_BOUNCES`.
+#[derive(Clone)]
+pub struct ProgramBuilder {
+    source: String,
+    name: String,
+    entry_point: String,
+    defines: Vec<(String, Option<String>)>,
+    includes: ShaderIncludes,
+    optimization_level: OptimizationLevel,
+    debug_info: bool,
+    warnings_as_errors: bool,
+    target_environment: TargetEnvironment,
+    glsl_version: Option<u32>,
+}
+
+impl ProgramBuilder {
+    pub fn new(
+        source: impl Into<String>,
+        name: impl Into<String>,
+        entry_point: impl Into<String>,
+    ) -> Self {
+        ProgramBuilder {
+            source: source.into(),
+            name: name.into(),
+            entry_point: entry_point.into(),
+            defines: Vec::new(),
+            includes: ShaderIncludes::builtin(),
+            optimization_level: OptimizationLevel::default(),
+            debug_info: false,
+            warnings_as_errors: false,
+            target_environment: TargetEnvironment::default(),
+            glsl_version: None,
+        }
+    }
+
+    /// Reads the source from `path`, which also names the shader in errors and is where
+    /// `#include "file"` looks first.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        entry_point: impl Into<String>,
+    ) -> Result<Self, ProgramError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|_| ProgramError::SourceReadFailed(path.display().to_string()))?;
+        Ok(Self::new(source, path.display().to_string(), entry_point))
+    }
+
+    /// Defines a macro as if by `#define name value`, replacing an earlier definition of `name`.
+    pub fn define(self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.add_define(name.into(), Some(value.to_string()))
+    }
+
+    /// Defines a macro without a value, for use with `#ifdef`.
+    pub fn flag(self, name: impl Into<String>) -> Self {
+        self.add_define(name.into(), None)
+    }
+
+    /// Replaces the built-in shader library used to resolve `#include`.
+    pub fn includes(mut self, includes: ShaderIncludes) -> Self {
+        self.includes = includes;
+        self
+    }
+
+    pub fn optimization_level(mut self, level: OptimizationLevel) -> Self {
+        self.optimization_level = level;
+        self
+    }
+
+    /// Keeps names and source lines in the SPIR-V, for debuggers such as RenderDoc.
+    pub fn debug_info(mut self, debug_info: bool) -> Self {
+        self.debug_info = debug_info;
+        self
+    }
+
+    pub fn warnings_as_errors(mut self, warnings_as_errors: bool) -> Self {
+        self.warnings_as_errors = warnings_as_errors;
+        self
+    }
+
+    /// Must not be newer than the `api_version` of the device, see [`DeviceInfo`].
+    pub fn target_environment(mut self, target: TargetEnvironment) -> Self {
+        self.target_environment = target;
+        self
+    }
+
+    /// Compiles as this GLSL version (such as `460`), overriding any `#version` directive.
+    pub fn glsl_version(mut self, version: u32) -> Self {
+        self.glsl_version = Some(version);
+        self
+    }
+
+    /// Compiles the program, or reuses the SPIR-V of an earlier build with the same source,
+    /// options and includes if the instance has a [`InstanceBuilder::cache_directory`]. Warnings
+    /// are not cached, so [`Program::warnings`] is empty for a cached module.
+    pub fn build(&self, instance: &Instance) -> Result<Program, ProgramError> {
+        let target = self.target_environment.version();
+        let api_version = instance.device_info().api_version;
+        if (target.major, target.minor) > (api_version.major, api_version.minor) {
+            return Err(ProgramError::UnsupportedTargetEnvironment(
+                self.target_environment,
+            ));
+        }
+
+        let cache = instance.shader_cache.as_deref();
+        let key = self.cache_key();
+        let cached = cache.and_then(|cache| cache.load_spirv(key, &self.includes));
+
+        let (spirv, warnings, dependencies) = match cached {
+            Some((spirv, dependencies)) => (spirv, Vec::new(), dependencies),
+            None => {
+                let (spirv, warnings, dependencies) = self.compile_with_dependencies()?;
+                if let Some(cache) = cache {
+                    cache.store_spirv(key, &spirv, &dependencies);
+                }
+                (spirv, warnings, dependencies)
+            }
+        };
+
+        let mut program = Program::from_compiled(instance, &spirv, &self.entry_point, warnings)?;
+        for dependency in dependencies {
+            let path = PathBuf::from(&dependency.resolved_name);
+            if !self.includes.is_virtual(&dependency.resolved_name)
+                && !program.included_files.contains(&path)
+            {
+                program.included_files.push(path);
+            }
+        }
+
+        Ok(program)
+    }
+
+    /// Compiles to SPIR-V without creating a pipeline, for example to save it and load it later
+    /// with [`Program::from_spirv_file`].
+    pub fn compile(&self) -> Result<Vec<u32>, ProgramError> {
+        self.compile_with_dependencies().map(|(spirv, _, _)| spirv)
+    }
+
+    /// Compiles to SPIR-V, also returning the warnings and every file that was included.
+    fn compile_with_dependencies(
+        &self,
+    ) -> Result<(Vec<u32>, Vec<Diagnostic>, Vec<Dependency>), ProgramError> {
+        let dependencies = RefCell::new(Vec::new());
+        let compiler =
+            shaderc::Compiler::new().map_err(|e| ProgramError::CompilerFailed(e.to_string()))?;
+        let spirv = compiler
+            .compile_into_spirv(
+                &self.source,
+                shaderc::ShaderKind::Compute,
+                &self.name,
+                &self.entry_point,
+                Some(&self.compile_options(&dependencies)?),
+            )
+            .map_err(|e| match e {
+                shaderc::Error::CompilationError(_, error_info) => {
+                    ProgramError::CompilationFailed(Diagnostic::parse(&error_info))
+                }
+                e => ProgramError::CompilerFailed(e.to_string()),
+            })?;
+
+        Ok((
+            spirv.as_binary().to_vec(),
+            Diagnostic::parse(&spirv.get_warning_messages()),
+            dependencies.into_inner(),
+        ))
+    }
+
+    /// Hash of everything besides the included files that determines the compiled module.
+    ///
+    /// Fields are written as explicit little endian bytes, the output of `std::hash::Hash` may
+    /// change between Rust releases. Strings are length prefixed so they can't run together.
+    fn cache_key(&self) -> u64 {
+        fn write_str(hasher: &mut Fnv1a, s: &str) {
+            hasher.write(&(s.len() as u64).to_le_bytes());
+            hasher.write(s.as_bytes());
+        }
+
+        let mut hasher = Fnv1a::new();
+
+        // the generator revision is glslang's, so an updated compiler invalidates the cache
+        let (spirv_version, generator_revision) = shaderc::get_spirv_version();
+        write_str(&mut hasher, env!("CARGO_PKG_VERSION"));
+        hasher.write(&spirv_version.to_le_bytes());
+        hasher.write(&generator_revision.to_le_bytes());
+
+        write_str(&mut hasher, &self.source);
+        write_str(&mut hasher, &self.name);
+        write_str(&mut hasher, &self.entry_point);
+        hasher.write(&(self.defines.len() as u64).to_le_bytes());
+        for (name, value) in &self.defines {
+            write_str(&mut hasher, name);
+            match value {
+                Some(value) => {
+                    hasher.write(&[1]);
+                    write_str(&mut hasher, value);
+                }
+                None => hasher.write(&[0]),
+            }
+        }
+        hasher.write(&[
+            self.optimization_level as u8,
+            self.debug_info as u8,
+            self.warnings_as_errors as u8,
+            self.target_environment as u8,
+        ]);
+        match self.glsl_version {
+            Some(version) => {
+                hasher.write(&[1]);
+                hasher.write(&version.to_le_bytes());
+            }
+            None => hasher.write(&[0]),
+        }
+        hasher.finish()
+    }
+
+    fn add_define(mut self, name: String, value: Option<String>) -> Self {
+        self.defines.retain(|(defined, _)| *defined != name);
+        self.defines.push((name, value));
+        self
+    }
+
+    fn compile_options<'a>(
+        &'a self,
+        dependencies: &'a RefCell<Vec<Dependency>>,
+    ) -> Result<shaderc::CompileOptions<'a>, ProgramError> {
+        let mut options = shaderc::CompileOptions::new()
+            .map_err(|e| ProgramError::CompilerFailed(e.to_string()))?;
+
+        for (name, value) in &self.defines {
+            options.add_macro_definition(name, value.as_deref());
+        }
+
+        options.set_include_callback(|requested, include_type, requesting, _| {
+            let include = self.includes.resolve(requested, include_type, requesting)?;
+            dependencies.borrow_mut().push(Dependency {
+                relative: include_type == shaderc::IncludeType::Relative,
+                requesting: requesting.to_string(),
+                requested: requested.to_string(),
+                resolved_name: include.resolved_name.clone(),
+                hash: Fnv1a::hash(include.content.as_bytes()),
+            });
+            Ok(include)
+        });
+        options.set_optimization_level(self.optimization_level.into());
+        options.set_target_env(
+            shaderc::TargetEnv::Vulkan,
+            shaderc::EnvVersion::from(self.target_environment) as u32,
+        );
+
+        if self.debug_info {
+            options.set_generate_debug_info();
+        }
+
+        if self.warnings_as_errors {
+            options.set_warnings_as_errors();
+        }
+
+        if let Some(version) = self.glsl_version {
+            options.set_forced_version_profile(version, shaderc::GlslProfile::None);
+        }
+
+        Ok(options)
+    }
+}
+
+#[derive(Clone)]
 pub struct Program {
-    warnings: String,
+    warnings: Vec<Diagnostic>,
+    reflection: Reflection,
+    included_files: Vec<PathBuf>,
     pub(super) compute_pipeline: Arc<vk::ComputePipeline>,
 }
 
 impl Program {
+    /// Compiles `source` with the default options of [`ProgramBuilder`], with the built-in
+    /// shader library available to `#include`.
     pub fn new(
         instance: &Instance,
         source: &str,
         name: &str,
         entry_point: &str,
     ) -> Result<Program, ProgramError> {
-        let compiler = shaderc::Compiler::new().unwrap();
-
-        let spirv = match compiler.compile_into_spirv(
-            source,
-            shaderc::ShaderKind::Compute,
-            name,
-            entry_point,
-            None,
-        ) {
-            Ok(result) => result,
-            Err(shaderc::Error::CompilationError(_, error_info)) => {
-                return Err(ProgramError::CompilationFailed(error_info))
-            }
-            Err(e) => panic!("unknown SPIR-V compile error: {:?}", e),
-        };
+        ProgramBuilder::new(source, name, entry_point).build(instance)
+    }
+
+    pub fn with_includes(
+        instance: &Instance,
+        source: &str,
+        name: &str,
+        entry_point: &str,
+        includes: &ShaderIncludes,
+    ) -> Result<Program, ProgramError> {
+        ProgramBuilder::new(source, name, entry_point)
+            .includes(includes.clone())
+            .build(instance)
+    }
+
+    /// Creates a program from SPIR-V compiled ahead of time, by [`ProgramBuilder::compile`] or
+    /// any other toolchain, without needing shaderc.
+    ///
+    /// # Safety
+    ///
+    /// `spirv` must be a valid SPIR-V module for the device, as written by a conforming compiler
+    /// such as shaderc or glslc. It is only checked to parse and to declare the entry point, the
+    /// driver trusts the rest of it.
+    pub unsafe fn from_spirv(
+        instance: &Instance,
+        spirv: &[u32],
+        entry_point: &str,
+    ) -> Result<Program, ProgramError> {
+        Self::from_compiled(instance, spirv, entry_point, Vec::new())
+    }
+
+    /// Loads a `.spv` file in either byte order, see [`Program::from_spirv`].
+    ///
+    /// # Safety
+    ///
+    /// The file must hold a valid SPIR-V module, as for [`Program::from_spirv`].
+    pub unsafe fn from_spirv_file(
+        instance: &Instance,
+        path: impl AsRef<Path>,
+        entry_point: &str,
+    ) -> Result<Program, ProgramError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|_| ProgramError::SpirvReadFailed(path.display().to_string()))?;
+        let spirv = spirv_from_bytes(&bytes).ok_or(ProgramError::InvalidSpirv)?;
+        Self::from_spirv(instance, &spirv, entry_point)
+    }
+
+    fn from_compiled(
+        instance: &Instance,
+        spirv: &[u32],
+        entry_point: &str,
+        warnings: Vec<Diagnostic>,
+    ) -> Result<Program, ProgramError> {
+        // vulkano keeps its parsed module private, the block layouts are read from this one
+        let parsed = vk::spirv::Spirv::new(spirv).map_err(|_| ProgramError::InvalidSpirv)?;
 
         let shared_module = {
             unsafe {
                 vk::ShaderModule::new(
                     instance.device.clone(),
-                    vk::ShaderModuleCreateInfo::new(spirv.as_binary()),
+                    vk::ShaderModuleCreateInfo::new(spirv),
                 )
                 .map_err(|_| ProgramError::VulkanShaderModuleCreationFailed)?
                 .entry_point(entry_point)
@@ -57,6 +441,8 @@ impl Program {
             }
         };
 
+        let reflection = Reflection::new(&parsed, &shared_module)?;
+
         let stage = vk::PipelineShaderStageCreateInfo::new(shared_module);
 
         let layout = vk::PipelineLayout::new(
@@ -69,19 +455,64 @@ impl Program {
 
         let compute_pipeline = vk::ComputePipeline::new(
             instance.device.clone(),
-            None,
+            instance
+                .shader_cache
+                .as_ref()
+                .map(|cache| cache.pipeline_cache.clone()),
             vk::ComputePipelineCreateInfo::stage_layout(stage, layout),
         )
         .map_err(|_| ProgramError::VulkanPipelineCreationFailed)?;
 
         Ok(Program {
-            warnings: spirv.get_warning_messages(),
+            warnings,
+            reflection,
+            included_files: Vec::new(),
             compute_pipeline,
         })
     }
 
+    /// Warnings of the compilation as text, one per line.
     pub fn get_warnings(&self) -> String {
-        self.warnings.clone()
+        display_diagnostics(&self.warnings)
+    }
+
+    /// Warnings of the compilation, empty for programs loaded from SPIR-V or the cache.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Files on disk pulled in by `#include`, in the order they were first included. Built-in
+    /// and other virtual files are not listed.
+    pub fn included_files(&self) -> &[PathBuf] {
+        &self.included_files
+    }
+
+    /// Descriptor bindings and local size of the entry point, read from the compiled SPIR-V.
+    pub fn reflection(&self) -> &Reflection {
+        &self.reflection
+    }
+}
+
+/// Serializes SPIR-V words in little endian byte order, as written by `glslc`.
+pub fn spirv_to_bytes(spirv: &[u32]) -> Vec<u8> {
+    spirv.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Reads SPIR-V words from bytes in either byte order, detected from the magic number.
+pub fn spirv_from_bytes(bytes: &[u8]) -> Option<Vec<u32>> {
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+
+    let words = bytes
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    match words.clone().next()? {
+        reflection::MAGIC => Some(words.collect()),
+        magic if magic.swap_bytes() == reflection::MAGIC => {
+            Some(words.map(u32::swap_bytes).collect())
+        }
+        _ => None,
     }
 }
 
@@ -109,6 +540,33 @@ mod tests {
         assert!(Program::new(&instance, &code, "test.glsl", "main").is_err());
     }
 
+    #[test]
+    fn reflection() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 16, local_size_y = 16, local_size_z = 1) in;
+            layout(binding = 0) buffer Image { vec4 image[]; };
+            layout(binding = 1) buffer Properties { float exposure; uint tone_operator; } properties;
+            void main() { image[gl_GlobalInvocationID.x] *= properties.exposure; }
+        ";
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let reflection = program.reflection();
+
+        assert_eq!(reflection.local_size, [16, 16, 1]);
+        assert_eq!(reflection.sets(), [0]);
+        assert_eq!(reflection.bindings.len(), 2);
+
+        let image = reflection.binding(0, 0).unwrap();
+        assert_eq!(image.name, "Image");
+        assert_eq!(image.kind, DescriptorKind::StorageBuffer);
+        assert_eq!(image.runtime_array_stride, Some(16));
+
+        let properties = reflection.binding(0, 1).unwrap();
+        assert_eq!(properties.name, "properties");
+        assert_eq!(properties.block_size, 8);
+    }
+
     #[test]
     fn compile_entry_point_error() {
         let code = r"
@@ -118,4 +576,159 @@ mod tests {
         let instance = Instance::new().unwrap();
         assert!(Program::new(&instance, &code, "test.glsl", "not_main").is_err());
     }
+
+    fn run_u32(instance: &Instance, program: &Program) -> u32 {
+        let buffer = CpuBuffer::from_vec(instance, vec![0u32]).unwrap();
+        TaskBuilder::new(instance)
+            .unwrap()
+            .run_program(program, (1, 1, 1), vec![buffer.bind(0)])
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+        buffer.read().unwrap()[0]
+    }
+
+    #[test]
+    fn defines() {
+        let code = r"
+            #version 460
+            layout(binding = 0) buffer Data { uint data; };
+            void main() {
+            #ifdef DOUBLE
+                data = 2 * VALUE;
+            #else
+                data = VALUE;
+            #endif
+            }
+        ";
+        let instance = Instance::new().unwrap();
+        let builder = ProgramBuilder::new(code, "test.glsl", "main").define("VALUE", 7);
+
+        let program = builder.build(&instance).unwrap();
+        assert_eq!(run_u32(&instance, &program), 7);
+
+        let program = builder.clone().flag("DOUBLE").build(&instance).unwrap();
+        assert_eq!(run_u32(&instance, &program), 14);
+
+        let program = builder.define("VALUE", "3u").build(&instance).unwrap();
+        assert_eq!(run_u32(&instance, &program), 3);
+    }
+
+    #[test]
+    fn compile_options() {
+        let code = r"
+            layout(binding = 0) buffer Data { uint data; };
+            void main() { data = 42; }
+        ";
+        let instance = Instance::new().unwrap();
+
+        assert!(Program::new(&instance, code, "test.glsl", "main").is_err());
+
+        for level in [
+            OptimizationLevel::Zero,
+            OptimizationLevel::Size,
+            OptimizationLevel::Performance,
+        ] {
+            let program = ProgramBuilder::new(code, "test.glsl", "main")
+                .glsl_version(460)
+                .optimization_level(level)
+                .debug_info(true)
+                .target_environment(TargetEnvironment::Vulkan1_0)
+                .build(&instance)
+                .unwrap();
+            assert_eq!(run_u32(&instance, &program), 42);
+        }
+    }
+
+    #[test]
+    fn warnings_as_errors() {
+        let code = r"
+            #version 460
+            #extension GL_EXT_not_a_real_extension : warn
+            void main() {}
+        ";
+        let instance = Instance::new().unwrap();
+        let builder = ProgramBuilder::new(code, "test.glsl", "main");
+
+        let program = builder.build(&instance).unwrap();
+        assert!(program
+            .get_warnings()
+            .contains("GL_EXT_not_a_real_extension"));
+        assert!(program
+            .warnings()
+            .iter()
+            .all(|d| d.severity == DiagnosticSeverity::Warning));
+
+        assert!(matches!(
+            builder.warnings_as_errors(true).build(&instance),
+            Err(ProgramError::CompilationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let code = "#version 460\nvoid main() {\n    undeclared = 1;\n}\n";
+        let instance = Instance::new().unwrap();
+
+        let error = Program::new(&instance, code, "test.glsl", "main")
+            .err()
+            .unwrap();
+        let ProgramError::CompilationFailed(diagnostics) = &error else {
+            panic!("unexpected error: {:?}", error);
+        };
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.file.as_deref(), Some("test.glsl"));
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
+        assert!(diagnostic.message.contains("undeclared"));
+        assert!(error.to_string().contains("test.glsl:3: error:"));
+    }
+
+    #[test]
+    fn from_spirv() {
+        let code = r"
+            #version 460
+            layout(binding = 0) buffer Data { uint data; };
+            void main() { data = 42; }
+        ";
+        let instance = Instance::new().unwrap();
+        let spirv = ProgramBuilder::new(code, "test.glsl", "main")
+            .compile()
+            .unwrap();
+
+        // SAFETY: the SPIR-V comes from shaderc, and the truncated module is rejected by parsing
+        let program = unsafe { Program::from_spirv(&instance, &spirv, "main") }.unwrap();
+        assert_eq!(run_u32(&instance, &program), 42);
+        assert!(matches!(
+            unsafe { Program::from_spirv(&instance, &spirv, "not_main") },
+            Err(ProgramError::EntryPointNotFound(_))
+        ));
+        assert!(unsafe { Program::from_spirv(&instance, &spirv[..4], "main") }.is_err());
+
+        let path = std::env::temp_dir().join(format!(
+            "voxel_renderer_from_spirv_{}.spv",
+            std::process::id()
+        ));
+        std::fs::write(&path, spirv_to_bytes(&spirv)).unwrap();
+        let program = unsafe { Program::from_spirv_file(&instance, &path, "main") }.unwrap();
+        assert_eq!(run_u32(&instance, &program), 42);
+
+        let swapped: Vec<_> = spirv.iter().map(|word| word.swap_bytes()).collect();
+        std::fs::write(&path, spirv_to_bytes(&swapped)).unwrap();
+        let program = unsafe { Program::from_spirv_file(&instance, &path, "main") }.unwrap();
+        assert_eq!(run_u32(&instance, &program), 42);
+
+        std::fs::write(&path, [1, 2, 3, 4, 5]).unwrap();
+        assert!(matches!(
+            unsafe { Program::from_spirv_file(&instance, &path, "main") },
+            Err(ProgramError::InvalidSpirv)
+        ));
+        assert!(matches!(
+            unsafe { Program::from_spirv_file(&instance, path.with_extension("missing"), "main") },
+            Err(ProgramError::SpirvReadFailed(_))
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
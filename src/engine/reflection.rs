@@ -0,0 +1,495 @@
+use super::*;
+use thiserror::Error;
+use vk::spirv::{BuiltIn, Decoration, ExecutionMode, Id, Instruction, Spirv, StorageClass};
+
+#[derive(Error, Debug, Clone)]
+pub enum ReflectionError {
+    #[error("failed to find entry point \"{0}\"")]
+    EntryPointNotFound(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DescriptorKind {
+    StorageBuffer,
+    UniformBuffer,
+    StorageImage,
+    SampledImage,
+    CombinedImageSampler,
+    Sampler,
+    Other,
+}
+
+impl From<vk::DescriptorType> for DescriptorKind {
+    fn from(descriptor_type: vk::DescriptorType) -> Self {
+        match descriptor_type {
+            vk::DescriptorType::StorageBuffer | vk::DescriptorType::StorageBufferDynamic => {
+                DescriptorKind::StorageBuffer
+            }
+            vk::DescriptorType::UniformBuffer | vk::DescriptorType::UniformBufferDynamic => {
+                DescriptorKind::UniformBuffer
+            }
+            vk::DescriptorType::StorageImage => DescriptorKind::StorageImage,
+            vk::DescriptorType::SampledImage => DescriptorKind::SampledImage,
+            vk::DescriptorType::CombinedImageSampler => DescriptorKind::CombinedImageSampler,
+            vk::DescriptorType::Sampler => DescriptorKind::Sampler,
+            _ => DescriptorKind::Other,
+        }
+    }
+}
+
+/// A descriptor used by an entry point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BindingInfo {
+    pub set: u32,
+    pub binding: u32,
+    /// Name of the variable, or of its block if the variable has no instance name.
+    pub name: String,
+    pub kind: DescriptorKind,
+    /// Number of descriptors for arrays of resources, 0 for runtime arrays, 1 otherwise.
+    pub count: u32,
+    /// Size in bytes of a buffer block without its trailing runtime array, 0 for other kinds.
+    pub block_size: u32,
+    /// Size in bytes of one element of the trailing runtime array of a buffer block.
+    pub runtime_array_stride: Option<u32>,
+}
+
+impl BindingInfo {
+    /// Smallest buffer in bytes that can back the binding.
+    pub fn min_buffer_size(&self) -> u64 {
+        self.block_size as u64
+    }
+}
+
+/// Interface of a compute entry point.
+///
+/// Descriptors and push constants come from vulkano's reflection of the entry point, which only
+/// lists the descriptors the entry point uses. Vulkano doesn't keep the names and layouts of
+/// buffer blocks, so those are looked up in the SPIR-V here.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reflection {
+    pub entry_point: String,
+    pub local_size: [u32; 3],
+    /// Every descriptor used by the entry point, sorted by set and binding.
+    pub bindings: Vec<BindingInfo>,
+    /// Size in bytes of the push constant block, if the entry point uses one.
+    pub push_constant_size: Option<u32>,
+}
+
+impl Reflection {
+    /// Reflects `entry_point`, which must belong to a module created from `spirv`.
+    pub fn new(spirv: &Spirv, entry_point: &vk::EntryPoint) -> Result<Reflection, ReflectionError> {
+        let info = entry_point.info();
+
+        let mut bindings: Vec<BindingInfo> = info
+            .descriptor_binding_requirements
+            .iter()
+            .map(|(&(set, binding), requirements)| {
+                let kind = requirements
+                    .descriptor_types
+                    .first()
+                    .map_or(DescriptorKind::Other, |&t| t.into());
+                let (name, block_size, runtime_array_stride) =
+                    block_info(spirv, set, binding, kind);
+
+                BindingInfo {
+                    set,
+                    binding,
+                    name,
+                    kind,
+                    count: requirements.descriptor_count.unwrap_or(0),
+                    block_size,
+                    runtime_array_stride,
+                }
+            })
+            .collect();
+        bindings.sort_by_key(|b| (b.set, b.binding));
+
+        Ok(Reflection {
+            entry_point: info.name.clone(),
+            local_size: local_size(spirv, &info.name)?,
+            bindings,
+            push_constant_size: info
+                .push_constant_requirements
+                .as_ref()
+                .map(|range| range.size),
+        })
+    }
+
+    /// Descriptor set numbers used by the entry point, in ascending order.
+    pub fn sets(&self) -> Vec<u32> {
+        let mut sets: Vec<u32> = self.bindings.iter().map(|b| b.set).collect();
+        sets.dedup();
+        sets
+    }
+
+    pub fn binding(&self, set: u32, binding: u32) -> Option<&BindingInfo> {
+        self.bindings
+            .iter()
+            .find(|b| b.set == set && b.binding == binding)
+    }
+}
+
+pub(super) const MAGIC: u32 = 0x0723_0203;
+
+fn local_size(spirv: &Spirv, entry_point: &str) -> Result<[u32; 3], ReflectionError> {
+    let function = spirv
+        .iter_entry_point()
+        .find_map(|instruction| match instruction {
+            Instruction::EntryPoint {
+                entry_point: id,
+                name,
+                ..
+            } if name == entry_point => Some(*id),
+            _ => None,
+        })
+        .ok_or_else(|| ReflectionError::EntryPointNotFound(entry_point.to_string()))?;
+
+    // a constant decorated with the WorkgroupSize built-in overrides the execution mode
+    let workgroup_size = spirv
+        .iter_decoration()
+        .find_map(|instruction| match instruction {
+            Instruction::Decorate {
+                target,
+                decoration:
+                    Decoration::BuiltIn {
+                        built_in: BuiltIn::WorkgroupSize,
+                    },
+            } => Some(*target),
+            _ => None,
+        });
+    if let Some(id) = workgroup_size {
+        if let Instruction::ConstantComposite { constituents, .. }
+        | Instruction::SpecConstantComposite { constituents, .. } = spirv.id(id).instruction()
+        {
+            if let [x, y, z] = constituents[..] {
+                return Ok([constant(spirv, x), constant(spirv, y), constant(spirv, z)]);
+            }
+        }
+    }
+
+    for instruction in spirv.iter_execution_mode() {
+        match instruction {
+            Instruction::ExecutionMode {
+                entry_point,
+                mode:
+                    ExecutionMode::LocalSize {
+                        x_size,
+                        y_size,
+                        z_size,
+                    },
+            } if *entry_point == function => return Ok([*x_size, *y_size, *z_size]),
+            Instruction::ExecutionModeId {
+                entry_point,
+                mode:
+                    ExecutionMode::LocalSizeId {
+                        x_size,
+                        y_size,
+                        z_size,
+                    },
+            } if *entry_point == function => {
+                return Ok([
+                    constant(spirv, *x_size),
+                    constant(spirv, *y_size),
+                    constant(spirv, *z_size),
+                ])
+            }
+            _ => {}
+        }
+    }
+
+    Ok([1; 3])
+}
+
+/// Value of a scalar constant, using the default of specialization constants.
+fn constant(spirv: &Spirv, id: Id) -> u32 {
+    match spirv.id(id).instruction() {
+        Instruction::Constant { value, .. } | Instruction::SpecConstant { value, .. } => {
+            value.first().copied().unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+/// Name, block size and runtime array stride of the variable at `set` and `binding`.
+fn block_info(
+    spirv: &Spirv,
+    set: u32,
+    binding: u32,
+    kind: DescriptorKind,
+) -> (String, u32, Option<u32>) {
+    let variable =
+        spirv
+            .iter_global()
+            .find_map(|instruction| match instruction {
+                Instruction::Variable {
+                    result_type_id,
+                    result_id,
+                    storage_class:
+                        StorageClass::Uniform
+                        | StorageClass::UniformConstant
+                        | StorageClass::StorageBuffer,
+                    ..
+                } if descriptor_location(spirv, *result_id) == (Some(set), Some(binding)) => {
+                    Some((*result_id, *result_type_id))
+                }
+                _ => None,
+            });
+    let Some((variable, pointer_type)) = variable else {
+        return (String::new(), 0, None);
+    };
+
+    let pointee = match spirv.id(pointer_type).instruction() {
+        Instruction::TypePointer { ty, .. } => *ty,
+        _ => pointer_type,
+    };
+    let element = match spirv.id(pointee).instruction() {
+        Instruction::TypeArray { element_type, .. }
+        | Instruction::TypeRuntimeArray { element_type, .. } => *element_type,
+        _ => pointee,
+    };
+
+    let name = match name(spirv, variable) {
+        variable_name if !variable_name.is_empty() => variable_name,
+        _ => name(spirv, element),
+    };
+
+    match kind {
+        DescriptorKind::StorageBuffer | DescriptorKind::UniformBuffer => {
+            let (block_size, runtime_array_stride) = block_layout(spirv, element);
+            (name, block_size, runtime_array_stride)
+        }
+        _ => (name, 0, None),
+    }
+}
+
+fn descriptor_location(spirv: &Spirv, variable: Id) -> (Option<u32>, Option<u32>) {
+    let mut location = (Some(0), None);
+    for instruction in spirv.id(variable).decorations() {
+        match instruction {
+            Instruction::Decorate {
+                decoration: Decoration::DescriptorSet { descriptor_set },
+                ..
+            } => location.0 = Some(*descriptor_set),
+            Instruction::Decorate {
+                decoration: Decoration::Binding { binding_point },
+                ..
+            } => location.1 = Some(*binding_point),
+            _ => {}
+        }
+    }
+    location
+}
+
+fn name(spirv: &Spirv, id: Id) -> String {
+    spirv
+        .id(id)
+        .names()
+        .iter()
+        .find_map(|instruction| match instruction {
+            Instruction::Name { name, .. } => Some(name.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn array_stride(spirv: &Spirv, id: Id) -> Option<u32> {
+    spirv
+        .id(id)
+        .decorations()
+        .iter()
+        .find_map(|instruction| match instruction {
+            Instruction::Decorate {
+                decoration: Decoration::ArrayStride { array_stride },
+                ..
+            } => Some(*array_stride),
+            _ => None,
+        })
+}
+
+/// Size of the block without a trailing runtime array, and the stride of that array.
+fn block_layout(spirv: &Spirv, struct_id: Id) -> (u32, Option<u32>) {
+    let Instruction::TypeStruct { member_types, .. } = spirv.id(struct_id).instruction() else {
+        return (size_of(spirv, struct_id, None), None);
+    };
+
+    let mut size = 0;
+    let mut runtime_array_stride = None;
+
+    for (member, &member_type) in spirv.id(struct_id).members().iter().zip(member_types) {
+        let mut offset = 0;
+        let mut matrix_stride = None;
+        for instruction in member.decorations() {
+            match instruction {
+                Instruction::MemberDecorate {
+                    decoration: Decoration::Offset { byte_offset },
+                    ..
+                } => offset = *byte_offset,
+                Instruction::MemberDecorate {
+                    decoration:
+                        Decoration::MatrixStride {
+                            matrix_stride: stride,
+                        },
+                    ..
+                } => matrix_stride = Some(*stride),
+                _ => {}
+            }
+        }
+
+        if let Instruction::TypeRuntimeArray { element_type, .. } =
+            spirv.id(member_type).instruction()
+        {
+            runtime_array_stride = Some(
+                array_stride(spirv, member_type)
+                    .unwrap_or_else(|| size_of(spirv, *element_type, None)),
+            );
+            size = size.max(offset);
+        } else {
+            size = size.max(offset + size_of(spirv, member_type, matrix_stride));
+        }
+    }
+
+    (size, runtime_array_stride)
+}
+
+fn size_of(spirv: &Spirv, type_id: Id, matrix_stride: Option<u32>) -> u32 {
+    match spirv.id(type_id).instruction() {
+        Instruction::TypeBool { .. } => 4,
+        Instruction::TypeInt { width, .. } | Instruction::TypeFloat { width, .. } => width / 8,
+        Instruction::TypeVector {
+            component_type,
+            component_count,
+            ..
+        } => size_of(spirv, *component_type, None) * component_count,
+        Instruction::TypeMatrix {
+            column_type,
+            column_count,
+            ..
+        } => match matrix_stride {
+            Some(stride) => stride * column_count,
+            None => size_of(spirv, *column_type, None) * column_count,
+        },
+        Instruction::TypeArray {
+            element_type,
+            length,
+            ..
+        } => {
+            let stride = array_stride(spirv, type_id)
+                .unwrap_or_else(|| size_of(spirv, *element_type, matrix_stride));
+            stride * constant(spirv, *length)
+        }
+        Instruction::TypeStruct { .. } => block_layout(spirv, type_id).0,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reflect(source: &str) -> Reflection {
+        let instance = Instance::new().unwrap();
+        Program::new(&instance, source, "test.glsl", "main")
+            .unwrap()
+            .reflection()
+            .clone()
+    }
+
+    #[test]
+    fn bindings() {
+        let reflection = reflect(
+            r"
+            #version 460
+            layout(local_size_x = 8, local_size_y = 4, local_size_z = 1) in;
+            struct Camera { vec3 pos; float pad; mat3 rot; };
+            layout(binding = 0) buffer Image { vec4 image[]; };
+            layout(binding = 2) buffer SceneBuffer { uvec3 size; uint pad; uint data[]; } scene;
+            layout(set = 1, binding = 0) uniform CameraBuffer { Camera camera; };
+            layout(binding = 1, rgba32f) uniform image2D target;
+            layout(binding = 3) uniform sampler2D textures[4];
+            void main() {
+                vec4 color = texture(textures[1], vec2(0.0)) + vec4(camera.pos, 0.0);
+                imageStore(target, ivec2(0), color);
+                image[0] = color * float(scene.size.x) + float(scene.data[0]);
+            }
+            ",
+        );
+
+        assert_eq!(reflection.entry_point, "main");
+        assert_eq!(reflection.local_size, [8, 4, 1]);
+        assert_eq!(reflection.sets(), [0, 1]);
+        assert_eq!(reflection.push_constant_size, None);
+
+        let image = reflection.binding(0, 0).unwrap();
+        assert_eq!(image.name, "Image");
+        assert_eq!(image.kind, DescriptorKind::StorageBuffer);
+        assert_eq!(
+            (image.block_size, image.runtime_array_stride),
+            (0, Some(16))
+        );
+
+        let target = reflection.binding(0, 1).unwrap();
+        assert_eq!(target.name, "target");
+        assert_eq!(target.kind, DescriptorKind::StorageImage);
+
+        let scene = reflection.binding(0, 2).unwrap();
+        assert_eq!(scene.name, "scene");
+        assert_eq!(
+            (scene.block_size, scene.runtime_array_stride),
+            (16, Some(4))
+        );
+
+        let textures = reflection.binding(0, 3).unwrap();
+        assert_eq!(textures.kind, DescriptorKind::CombinedImageSampler);
+        assert_eq!(textures.count, 4);
+
+        let camera = reflection.binding(1, 0).unwrap();
+        assert_eq!(camera.name, "CameraBuffer");
+        assert_eq!(camera.kind, DescriptorKind::UniformBuffer);
+        assert_eq!((camera.block_size, camera.runtime_array_stride), (64, None));
+    }
+
+    #[test]
+    fn push_constants_and_default_local_size() {
+        let reflection = reflect(
+            r"
+            #version 460
+            layout(push_constant) uniform Frame { uint seed; uint samples; vec2 jitter; } frame;
+            layout(binding = 0) buffer Data { uint data[]; };
+            void main() { data[0] = frame.seed + frame.samples + uint(frame.jitter.x); }
+            ",
+        );
+
+        assert_eq!(reflection.local_size, [1, 1, 1]);
+        assert_eq!(reflection.push_constant_size, Some(16));
+        assert_eq!(reflection.bindings.len(), 1);
+    }
+
+    #[test]
+    fn workgroup_size_constant() {
+        let reflection = reflect(
+            r"
+            #version 460
+            layout(local_size_x_id = 0, local_size_y = 2) in;
+            layout(binding = 0) buffer Data { uint data[]; };
+            void main() { data[0] = gl_WorkGroupSize.x * gl_WorkGroupSize.y; }
+            ",
+        );
+
+        assert_eq!(reflection.local_size, [1, 2, 1]);
+    }
+
+    #[test]
+    fn unused_bindings() {
+        let reflection = reflect(
+            r"
+            #version 460
+            layout(binding = 0) buffer Data { uint data[]; };
+            layout(binding = 1) buffer Unused { uint unused[]; };
+            void main() { data[0] = 1; }
+            ",
+        );
+
+        assert_eq!(reflection.bindings.len(), 1);
+        assert!(reflection.binding(0, 1).is_none());
+    }
+}
@@ -19,6 +19,8 @@ pub enum TaskError {
     VulkanCommandBufferBuildFailed,
     #[error("failed to record vulkan copy buffer command")]
     VulkanCopyBufferFailed,
+    #[error("failed to record vulkan copy image command")]
+    VulkanCopyImageFailed,
     #[error("failed to create vulkan descriptor set")]
     VulkanDescriptorSetCreationFailed,
     #[error("failed to record vulkan descriptor bind command")]
@@ -27,6 +29,47 @@ pub enum TaskError {
     VulkanPipelineBindingFailed,
     #[error("failed to dispatch vulkan command buffer")]
     VulkanDispatchFailed,
+    #[error("failed to record vulkan push constants command")]
+    VulkanPushConstantsFailed,
+    #[error("binding {binding} (\"{name}\") is used by the program but was not provided")]
+    MissingBinding { binding: u32, name: String },
+    #[error("binding {0} is not used by the program")]
+    UnusedBinding(u32),
+    #[error("the program uses descriptor set {0}, but only set 0 can be bound")]
+    UnsupportedDescriptorSet(u32),
+    #[error("binding {0} was provided more than once")]
+    DuplicateBinding(u32),
+    #[error("binding {binding} (\"{name}\") expects a {expected:?} but a {found:?} was provided")]
+    BindingKindMismatch {
+        binding: u32,
+        name: String,
+        expected: DescriptorKind,
+        found: DescriptorKind,
+    },
+    #[error("binding {binding} (\"{name}\") is a {expected} byte uniform block, not {size} bytes")]
+    UniformSizeMismatch {
+        binding: u32,
+        name: String,
+        expected: u64,
+        size: u64,
+    },
+    #[error(
+        "binding {binding} (\"{name}\") needs at least {required} bytes but {size} were provided"
+    )]
+    BindingTooSmall {
+        binding: u32,
+        name: String,
+        required: u64,
+        size: u64,
+    },
+    #[error("the program expects {0} bytes of push constants but none were provided")]
+    MissingPushConstants(u32),
+    #[error("push constants were provided but the program doesn't use any")]
+    UnusedPushConstants,
+    #[error("the program expects {expected} bytes of push constants but {size} were provided")]
+    PushConstantSizeMismatch { expected: u32, size: u32 },
+    #[error("the image holds {image} bytes but the buffer holds {buffer}")]
+    ImageCopySizeMismatch { image: u64, buffer: u64 },
 }
 
 pub struct TaskFuture {
@@ -44,18 +87,32 @@ impl TaskFuture {
     }
 }
 
+type CommandBuffer = vk::PrimaryAutoCommandBuffer<Arc<vk::StandardCommandBufferAllocator>>;
+type CommandBufferBuilder =
+    vk::AutoCommandBufferBuilder<CommandBuffer, Arc<vk::StandardCommandBufferAllocator>>;
+
 pub struct Task {
     device: Arc<vk::Device>,
     queue: Arc<vk::Queue>,
-    command_buffer: Arc<vk::PrimaryAutoCommandBuffer<Arc<vk::StandardCommandBufferAllocator>>>,
+    /// One command buffer per pass, see [`TaskBuilder::end_pass`].
+    command_buffers: Vec<Arc<CommandBuffer>>,
 }
 
 impl Task {
     pub fn submit(&self) -> Result<TaskFuture, TaskError> {
-        let future = vk::sync::now(self.device.clone())
-            .then_execute(self.queue.clone(), self.command_buffer.clone())
-            .map_err(|_| TaskError::TaskSubmissionFailed)?
-            .boxed();
+        let mut future = vk::sync::now(self.device.clone()).boxed();
+
+        for (i, command_buffer) in self.command_buffers.iter().enumerate() {
+            // each pass is its own submission, started once the previous one has finished
+            if i > 0 {
+                future = future.then_signal_semaphore().boxed();
+            }
+            future = future
+                .then_execute(self.queue.clone(), command_buffer.clone())
+                .map_err(|_| TaskError::TaskSubmissionFailed)?
+                .boxed();
+        }
+
         Ok(TaskFuture { future })
     }
 
@@ -65,13 +122,21 @@ impl Task {
     }
 }
 
+/// Records copies and program dispatches into a [`Task`].
+///
+/// Commands run in the order they are recorded, so a task can chain passes such as "trace,
+/// then denoise, then tone map" through intermediate [`GpuBuffer`]s: a dispatch sees every
+/// write that earlier commands made to the buffers and images it binds, as the accesses of
+/// each command are synchronized automatically.
+///
+/// [`TaskBuilder::end_pass`] splits a long task into passes that are submitted separately.
 pub struct TaskBuilder {
     device: Arc<vk::Device>,
     queue: Arc<vk::Queue>,
-    builder: vk::AutoCommandBufferBuilder<
-        vk::PrimaryAutoCommandBuffer<Arc<vk::StandardCommandBufferAllocator>>,
-        Arc<vk::StandardCommandBufferAllocator>,
-    >,
+    queue_family_index: u32,
+    command_buffer_allocator: Arc<vk::StandardCommandBufferAllocator>,
+    command_buffers: Vec<Arc<CommandBuffer>>,
+    builder: CommandBufferBuilder,
 }
 
 impl TaskBuilder {
@@ -79,24 +144,46 @@ impl TaskBuilder {
         Ok(Self {
             device: instance.device.clone(),
             queue: instance.queue.clone(),
-            builder: vk::AutoCommandBufferBuilder::primary(
+            queue_family_index: instance.queue_family_index,
+            command_buffer_allocator: instance.command_buffer_allocator.clone(),
+            command_buffers: Vec::new(),
+            builder: command_buffer_builder(
                 &instance.command_buffer_allocator,
                 instance.queue_family_index,
-                vk::CommandBufferUsage::MultipleSubmit,
-            )
-            .map_err(|_| TaskError::VulkanCommandBufferBuilderCreationFailed)?,
+            )?,
         })
     }
 
-    pub fn build(self) -> Result<Task, TaskError> {
+    pub fn build(mut self) -> Result<Task, TaskError> {
+        self.command_buffers.push(
+            self.builder
+                .build()
+                .map_err(|_| TaskError::VulkanCommandBufferBuildFailed)?,
+        );
+
         Ok(Task {
             device: self.device,
             queue: self.queue,
-            command_buffer: self
-                .builder
+            command_buffers: self.command_buffers,
+        })
+    }
+
+    /// Starts a new command buffer, submitted after the current one has finished. Keeps long
+    /// tasks from running into the driver's timeout.
+    pub fn end_pass(mut self) -> Result<TaskBuilder, TaskError> {
+        let builder =
+            command_buffer_builder(&self.command_buffer_allocator, self.queue_family_index)?;
+        self.command_buffers.push(
+            std::mem::replace(&mut self.builder, builder)
                 .build()
                 .map_err(|_| TaskError::VulkanCommandBufferBuildFailed)?,
-        })
+        );
+        Ok(self)
+    }
+
+    /// Number of passes recorded so far, one more than the number of `end_pass` calls.
+    pub fn passes(&self) -> usize {
+        self.command_buffers.len() + 1
     }
 
     pub fn build_submit_and_wait(self) -> Result<(), TaskError> {
@@ -118,52 +205,275 @@ impl TaskBuilder {
         Ok(self)
     }
 
-    pub fn run_program(
+    /// Fills `dst` with the texels in `src`, which must have exactly the size of the image.
+    pub fn copy_buffer_to_image<T: BufferContents + Clone + Copy, BufferLoc>(
         mut self,
+        src: &Buffer<T, BufferLoc>,
+        dst: &Image,
+    ) -> Result<TaskBuilder, TaskError> {
+        validate_image_copy(dst, src.get_vk_buffer().size())?;
+        self.builder
+            .copy_buffer_to_image(vk::CopyBufferToImageInfo::buffer_image(
+                src.get_vk_buffer().clone(),
+                dst.get_vk_image().clone(),
+            ))
+            .map_err(|_| TaskError::VulkanCopyImageFailed)?;
+        Ok(self)
+    }
+
+    /// Reads every texel of `src` into `dst`, which must have exactly the size of the image.
+    pub fn copy_image_to_buffer<T: BufferContents + Clone + Copy, BufferLoc>(
+        mut self,
+        src: &Image,
+        dst: &Buffer<T, BufferLoc>,
+    ) -> Result<TaskBuilder, TaskError> {
+        validate_image_copy(src, dst.get_vk_buffer().size())?;
+        self.builder
+            .copy_image_to_buffer(vk::CopyImageToBufferInfo::image_buffer(
+                src.get_vk_image().clone(),
+                dst.get_vk_buffer().clone(),
+            ))
+            .map_err(|_| TaskError::VulkanCopyImageFailed)?;
+        Ok(self)
+    }
+
+    pub fn run_program(
+        self,
         program: &Program,
         wg_size: (usize, usize, usize),
         bindings: Vec<BufferBinding>,
     ) -> Result<TaskBuilder, TaskError> {
-        let descriptor_set_allocator =
-            vk::StandardDescriptorSetAllocator::new(self.device.clone(), Default::default());
-
-        let descriptor_set_layout = program
-            .compute_pipeline
-            .layout()
-            .set_layouts()
-            .get(0)
-            .unwrap();
+        self.dispatch::<u32>(program, GroupCount::direct(wg_size), bindings, None)
+    }
 
-        let descriptor_writes: Vec<vk::WriteDescriptorSet> = bindings
-            .iter()
-            .map(|b| b.write_descriptor_set.clone())
-            .collect();
+    /// Like [`TaskBuilder::run_program`], but reads the number of work groups in x, y and z from
+    /// the first element of `group_count` when the dispatch runs, so an earlier command in the
+    /// same task can decide how much work there is.
+    pub fn run_program_indirect<Location>(
+        self,
+        program: &Program,
+        group_count: &Buffer<[u32; 3], Location>,
+        bindings: Vec<BufferBinding>,
+    ) -> Result<TaskBuilder, TaskError> {
+        let indirect_buffer = group_count
+            .get_vk_buffer()
+            .clone()
+            .slice(0..1)
+            .reinterpret::<[vk::DispatchIndirectCommand]>();
+        self.dispatch::<u32>(
+            program,
+            GroupCount::Indirect(indirect_buffer),
+            bindings,
+            None,
+        )
+    }
 
-        let descriptor_set = vk::PersistentDescriptorSet::new(
-            &descriptor_set_allocator,
-            descriptor_set_layout.clone(),
-            descriptor_writes,
-            [],
+    /// Like [`TaskBuilder::run_program`], but also sets the program's `push_constant` block to
+    /// `push_constants`, which must have the size of the block.
+    pub fn run_program_with_push_constants<T: BufferContents + Copy>(
+        self,
+        program: &Program,
+        wg_size: (usize, usize, usize),
+        bindings: Vec<BufferBinding>,
+        push_constants: T,
+    ) -> Result<TaskBuilder, TaskError> {
+        self.dispatch(
+            program,
+            GroupCount::direct(wg_size),
+            bindings,
+            Some(push_constants),
         )
-        .map_err(|_| TaskError::VulkanDescriptorSetCreationFailed)?;
+    }
+
+    fn dispatch<T: BufferContents + Copy>(
+        mut self,
+        program: &Program,
+        group_count: GroupCount,
+        bindings: Vec<BufferBinding>,
+        push_constants: Option<T>,
+    ) -> Result<TaskBuilder, TaskError> {
+        validate_bindings(program, &bindings)?;
+        validate_push_constants(program, push_constants.map(|_| std::mem::size_of::<T>()))?;
 
         self.builder
             .bind_pipeline_compute(program.compute_pipeline.clone())
-            .map_err(|_| TaskError::VulkanPipelineBindingFailed)?
-            .bind_descriptor_sets(
-                vk::PipelineBindPoint::Compute,
-                program.compute_pipeline.layout().clone(),
-                0,
-                descriptor_set,
+            .map_err(|_| TaskError::VulkanPipelineBindingFailed)?;
+
+        if let Some(push_constants) = push_constants {
+            self.builder
+                .push_constants(program.compute_pipeline.layout().clone(), 0, push_constants)
+                .map_err(|_| TaskError::VulkanPushConstantsFailed)?;
+        }
+
+        if let Some(descriptor_set_layout) = program.compute_pipeline.layout().set_layouts().first()
+        {
+            let descriptor_set_allocator =
+                vk::StandardDescriptorSetAllocator::new(self.device.clone(), Default::default());
+
+            let descriptor_writes: Vec<vk::WriteDescriptorSet> = bindings
+                .iter()
+                .map(|b| b.write_descriptor_set.clone())
+                .collect();
+
+            let descriptor_set = vk::PersistentDescriptorSet::new(
+                &descriptor_set_allocator,
+                descriptor_set_layout.clone(),
+                descriptor_writes,
+                [],
             )
-            .map_err(|_| TaskError::VulkanDescriptorSetBindingFailed)?
-            .dispatch([wg_size.0 as u32, wg_size.1 as u32, wg_size.2 as u32])
-            .map_err(|_| TaskError::VulkanDispatchFailed)?;
+            .map_err(|_| TaskError::VulkanDescriptorSetCreationFailed)?;
+
+            self.builder
+                .bind_descriptor_sets(
+                    vk::PipelineBindPoint::Compute,
+                    program.compute_pipeline.layout().clone(),
+                    0,
+                    descriptor_set,
+                )
+                .map_err(|_| TaskError::VulkanDescriptorSetBindingFailed)?;
+        }
+
+        match group_count {
+            GroupCount::Direct(wg_size) => self.builder.dispatch(wg_size),
+            GroupCount::Indirect(indirect_buffer) => {
+                self.builder.dispatch_indirect(indirect_buffer)
+            }
+        }
+        .map_err(|_| TaskError::VulkanDispatchFailed)?;
 
         Ok(self)
     }
 }
 
+/// Number of work groups of a dispatch, given by the CPU or read from a buffer by the GPU.
+enum GroupCount {
+    Direct([u32; 3]),
+    Indirect(vk::Subbuffer<[vk::DispatchIndirectCommand]>),
+}
+
+impl GroupCount {
+    fn direct(wg_size: (usize, usize, usize)) -> Self {
+        GroupCount::Direct([wg_size.0 as u32, wg_size.1 as u32, wg_size.2 as u32])
+    }
+}
+
+/// Checks `bindings` against the bindings the program's pipeline uses, naming the first one
+/// that is missing or doesn't fit.
+fn validate_bindings(program: &Program, bindings: &[BufferBinding]) -> Result<(), TaskError> {
+    if let Some(&set) = program.reflection().sets().iter().find(|&&set| set != 0) {
+        return Err(TaskError::UnsupportedDescriptorSet(set));
+    }
+
+    let used: Vec<u32> = match program.compute_pipeline.layout().set_layouts().first() {
+        Some(layout) => layout.bindings().keys().copied().collect(),
+        None => Vec::new(),
+    };
+
+    let name = |binding: u32| {
+        program
+            .reflection()
+            .binding(0, binding)
+            .map(|info| info.name.clone())
+            .unwrap_or_default()
+    };
+
+    let missing = used
+        .iter()
+        .copied()
+        .filter(|&binding| !bindings.iter().any(|b| b.binding == binding))
+        .min();
+
+    if let Some(binding) = missing {
+        return Err(TaskError::MissingBinding {
+            binding,
+            name: name(binding),
+        });
+    }
+
+    for (i, binding) in bindings.iter().enumerate() {
+        if !used.contains(&binding.binding) {
+            return Err(TaskError::UnusedBinding(binding.binding));
+        }
+
+        if bindings[..i].iter().any(|b| b.binding == binding.binding) {
+            return Err(TaskError::DuplicateBinding(binding.binding));
+        }
+
+        let Some(info) = program.reflection().binding(0, binding.binding) else {
+            continue;
+        };
+
+        if info.kind != binding.kind {
+            return Err(TaskError::BindingKindMismatch {
+                binding: binding.binding,
+                name: info.name.clone(),
+                expected: info.kind,
+                found: binding.kind,
+            });
+        }
+
+        // a uniform block is laid out as a std140 struct, whose size is rounded up to 16 bytes
+        let uniform_size = info.min_buffer_size().next_multiple_of(16);
+        if info.kind == DescriptorKind::UniformBuffer && binding.size != uniform_size {
+            return Err(TaskError::UniformSizeMismatch {
+                binding: binding.binding,
+                name: info.name.clone(),
+                expected: uniform_size,
+                size: binding.size,
+            });
+        }
+
+        if binding.size < info.min_buffer_size() {
+            return Err(TaskError::BindingTooSmall {
+                binding: binding.binding,
+                name: info.name.clone(),
+                required: info.min_buffer_size(),
+                size: binding.size,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn command_buffer_builder(
+    allocator: &Arc<vk::StandardCommandBufferAllocator>,
+    queue_family_index: u32,
+) -> Result<CommandBufferBuilder, TaskError> {
+    vk::AutoCommandBufferBuilder::primary(
+        allocator,
+        queue_family_index,
+        vk::CommandBufferUsage::MultipleSubmit,
+    )
+    .map_err(|_| TaskError::VulkanCommandBufferBuilderCreationFailed)
+}
+
+fn validate_image_copy(image: &Image, buffer_size: u64) -> Result<(), TaskError> {
+    if image.byte_len() != buffer_size {
+        return Err(TaskError::ImageCopySizeMismatch {
+            image: image.byte_len(),
+            buffer: buffer_size,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that push constants of `size` bytes fill the program's push constant block exactly.
+fn validate_push_constants(program: &Program, size: Option<usize>) -> Result<(), TaskError> {
+    match (program.reflection().push_constant_size, size) {
+        (None, None) => Ok(()),
+        (Some(expected), None) => Err(TaskError::MissingPushConstants(expected)),
+        (None, Some(_)) => Err(TaskError::UnusedPushConstants),
+        (Some(expected), Some(size)) if size != expected as usize => {
+            Err(TaskError::PushConstantSizeMismatch {
+                expected,
+                size: size as u32,
+            })
+        }
+        (Some(_), Some(_)) => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,4 +628,286 @@ mod tests {
             .run_program(&program, (4, 1, 1), vec![buffer.bind(1)])
             .is_err());
     }
+
+    #[test]
+    fn run_program_binding_errors() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Data { uint data[]; };
+            layout(binding = 1) buffer Properties { uint scale; uint offset; } properties;
+            void main() {
+                data[gl_GlobalInvocationID.x] *= properties.scale;
+                data[gl_GlobalInvocationID.x] += properties.offset;
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let data = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+        let properties = CpuBuffer::from_vec(&instance, vec![2u32, 1]).unwrap();
+
+        let run = |bindings| {
+            TaskBuilder::new(&instance)
+                .unwrap()
+                .run_program(&program, (4, 1, 1), bindings)
+        };
+
+        assert!(matches!(
+            run(vec![data.bind(0)]),
+            Err(TaskError::MissingBinding { binding: 1, name }) if name == "properties"
+        ));
+        assert!(matches!(
+            run(vec![data.bind(0), properties.bind(1), data.bind(2)]),
+            Err(TaskError::UnusedBinding(2))
+        ));
+        assert!(matches!(
+            run(vec![data.bind(0), properties.bind(1), data.bind(1)]),
+            Err(TaskError::DuplicateBinding(1))
+        ));
+        assert!(matches!(
+            run(vec![data.bind(0), properties.sub(0..1).unwrap().bind(1)]),
+            Err(TaskError::BindingTooSmall {
+                binding: 1,
+                required: 8,
+                size: 4,
+                ..
+            })
+        ));
+
+        run(vec![data.bind(0), properties.bind(1)])
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+        assert_eq!(data.read().unwrap(), vec![3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn run_program_descriptor_set() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(set = 1, binding = 0) buffer Data { uint data[]; };
+            void main() { data[gl_GlobalInvocationID.x] += 1; }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let data = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+
+        assert!(matches!(
+            TaskBuilder::new(&instance).unwrap().run_program(
+                &program,
+                (4, 1, 1),
+                vec![data.bind(0)]
+            ),
+            Err(TaskError::UnsupportedDescriptorSet(1))
+        ));
+    }
+
+    #[test]
+    fn run_program_push_constants() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Data { uint data[]; };
+            layout(push_constant) uniform Parameters { uint scale; uint offset; } parameters;
+            void main() {
+                data[gl_GlobalInvocationID.x] *= parameters.scale;
+                data[gl_GlobalInvocationID.x] += parameters.offset;
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let buffer = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+
+        let run = |push_constants: Option<[u32; 2]>| {
+            let builder = TaskBuilder::new(&instance).unwrap();
+            match push_constants {
+                Some(push_constants) => builder.run_program_with_push_constants(
+                    &program,
+                    (4, 1, 1),
+                    vec![buffer.bind(0)],
+                    push_constants,
+                ),
+                None => builder.run_program(&program, (4, 1, 1), vec![buffer.bind(0)]),
+            }
+        };
+
+        assert!(matches!(run(None), Err(TaskError::MissingPushConstants(8))));
+        assert!(matches!(
+            TaskBuilder::new(&instance)
+                .unwrap()
+                .run_program_with_push_constants(&program, (4, 1, 1), vec![buffer.bind(0)], 1u32),
+            Err(TaskError::PushConstantSizeMismatch {
+                expected: 8,
+                size: 4
+            })
+        ));
+
+        let task = run(Some([2, 1])).unwrap().build().unwrap();
+        task.submit_and_wait().unwrap();
+        assert_eq!(buffer.read().unwrap(), vec![3, 5, 7, 9]);
+
+        run(Some([1, 10])).unwrap().build_submit_and_wait().unwrap();
+        assert_eq!(buffer.read().unwrap(), vec![13, 15, 17, 19]);
+
+        let code = r"
+            #version 460
+            layout(binding = 0) buffer Data { uint data[]; };
+            void main() { data[0] = 1; }
+        ";
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        assert!(matches!(
+            TaskBuilder::new(&instance)
+                .unwrap()
+                .run_program_with_push_constants(&program, (1, 1, 1), vec![buffer.bind(0)], 1u32),
+            Err(TaskError::UnusedPushConstants)
+        ));
+    }
+
+    #[test]
+    fn multi_pass() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Src { uint src[]; };
+            layout(binding = 1) buffer Dst { uint dst[]; };
+            void main() {
+                uint i = gl_GlobalInvocationID.x;
+                dst[i] = src[i] * 2 + 1;
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let input = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+        let intermediate = GpuBuffer::<u32>::new(&instance, 4).unwrap();
+        let output = CpuBuffer::<u32>::new(&instance, 4).unwrap();
+
+        let builder = TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(
+                &program,
+                (4, 1, 1),
+                vec![input.bind(0), intermediate.bind(1)],
+            )
+            .unwrap()
+            .run_program(
+                &program,
+                (4, 1, 1),
+                vec![intermediate.bind(0), output.bind(1)],
+            )
+            .unwrap();
+        assert_eq!(builder.passes(), 1);
+
+        let task = builder.build().unwrap();
+        task.submit_and_wait().unwrap();
+        assert_eq!(output.read().unwrap(), vec![7, 11, 15, 19]);
+
+        input.write(vec![0, 0, 0, 0]).unwrap();
+        task.submit_and_wait().unwrap();
+        assert_eq!(output.read().unwrap(), vec![3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn end_pass() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Data { uint data[]; };
+            void main() { data[gl_GlobalInvocationID.x] += 1; }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let input = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+        let data = GpuBuffer::<u32>::new(&instance, 4).unwrap();
+        let output = CpuBuffer::<u32>::new(&instance, 4).unwrap();
+
+        // a long run of dispatches split into one submission per dispatch
+        let mut builder = TaskBuilder::new(&instance)
+            .unwrap()
+            .copy_buffer(&input, &data)
+            .unwrap();
+        for _ in 0..16 {
+            builder = builder
+                .end_pass()
+                .unwrap()
+                .run_program(&program, (4, 1, 1), vec![data.bind(0)])
+                .unwrap();
+        }
+        let builder = builder.copy_buffer(&data, &output).unwrap();
+        assert_eq!(builder.passes(), 17);
+
+        let task = builder.build().unwrap();
+        assert_eq!(task.command_buffers.len(), 17);
+
+        task.submit_and_wait().unwrap();
+        assert_eq!(output.read().unwrap(), vec![17, 18, 19, 20]);
+
+        task.submit_and_wait().unwrap();
+        assert_eq!(output.read().unwrap(), vec![17, 18, 19, 20]);
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .end_pass()
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+    }
+
+    #[test]
+    fn run_program_indirect() {
+        let count_code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Input { uint values[]; };
+            layout(binding = 1) buffer GroupCount { uint x; uint y; uint z; } group_count;
+            void main() {
+                uint count = 0;
+                for (uint i = 0; i < values.length(); i++) {
+                    count += values[i] > 2 ? 1 : 0;
+                }
+                group_count.x = count;
+                group_count.y = 2;
+                group_count.z = 1;
+            }
+        ";
+        let count_groups = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Counter { uint counter; };
+            void main() { atomicAdd(counter, 1); }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let count_program = Program::new(&instance, &count_code, "count.glsl", "main").unwrap();
+        let groups_program = Program::new(&instance, &count_groups, "groups.glsl", "main").unwrap();
+        let values = CpuBuffer::from_vec(&instance, vec![1u32, 5, 3, 2, 4]).unwrap();
+        let group_count = GpuBuffer::<[u32; 3]>::new(&instance, 1).unwrap();
+        let counter = CpuBuffer::from_vec(&instance, vec![0u32]).unwrap();
+
+        let task = TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(
+                &count_program,
+                (1, 1, 1),
+                vec![values.bind(0), group_count.bind(1)],
+            )
+            .unwrap()
+            .run_program_indirect(&groups_program, &group_count, vec![counter.bind(0)])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        task.submit_and_wait().unwrap();
+        assert_eq!(counter.read().unwrap(), vec![6]);
+
+        values.write(vec![3, 3, 3, 3, 3]).unwrap();
+        counter.write(vec![0]).unwrap();
+        task.submit_and_wait().unwrap();
+        assert_eq!(counter.read().unwrap(), vec![10]);
+    }
 }
@@ -0,0 +1,177 @@
+use super::*;
+use std::mem::size_of;
+
+/// A single host visible value bound as a uniform buffer, for small read-only parameters such
+/// as the camera.
+///
+/// Uniform blocks use the std140 layout, which rounds the size of structs up to a multiple of
+/// 16 bytes, so `T` has to be padded to one. [`UniformBuffer::new`] only checks that size, and
+/// [`TaskBuilder::run_program`] checks that it equals the size of the program's block. The
+/// offsets of the fields are not checked, so `T` has to follow std140 itself.
+#[derive(Clone)]
+pub struct UniformBuffer<T>
+where
+    T: BufferContents + Clone + Copy,
+{
+    buffer: vk::Subbuffer<T>,
+}
+
+impl<T> UniformBuffer<T>
+where
+    T: BufferContents + Clone + Copy,
+{
+    pub fn new(instance: &Instance, value: T) -> Result<UniformBuffer<T>, BufferError> {
+        if size_of::<T>() % 16 != 0 {
+            return Err(BufferError::NotStd140Sized(size_of::<T>()));
+        }
+
+        let buffer = vk::Buffer::from_data(
+            instance.memory_allocator.clone(),
+            vk::BufferCreateInfo {
+                usage: vk::BufferUsage::UNIFORM_BUFFER
+                    | vk::BufferUsage::TRANSFER_SRC
+                    | vk::BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            vk::AllocationCreateInfo {
+                memory_type_filter: vk::MemoryTypeFilter::PREFER_HOST
+                    | vk::MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            value,
+        )
+        .map_err(|_| BufferError::VulkanBufferCreationFailed)?;
+
+        Ok(UniformBuffer { buffer })
+    }
+
+    pub fn read(&self) -> Result<T, BufferError> {
+        Ok(*self
+            .buffer
+            .read()
+            .map_err(|_| BufferError::VulkanBufferReadFailed)?)
+    }
+
+    pub fn write(&self, value: T) -> Result<(), BufferError> {
+        *self
+            .buffer
+            .write()
+            .map_err(|_| BufferError::VulkanBufferWriteFailed)? = value;
+        Ok(())
+    }
+
+    pub fn bind(&self, binding: u32) -> BufferBinding {
+        BufferBinding {
+            binding,
+            kind: DescriptorKind::UniformBuffer,
+            size: self.buffer.size(),
+            write_descriptor_set: vk::WriteDescriptorSet::buffer(binding, self.buffer.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(BufferContents, Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct Parameters {
+        scale: u32,
+        offset: u32,
+        padding: [u32; 2],
+    }
+
+    #[test]
+    fn read_write() {
+        let instance = Instance::new().unwrap();
+        let value = Parameters {
+            scale: 2,
+            offset: 1,
+            padding: [0; 2],
+        };
+
+        let buffer = UniformBuffer::new(&instance, value).unwrap();
+        assert_eq!(buffer.read().unwrap(), value);
+
+        buffer.write(Parameters { scale: 3, ..value }).unwrap();
+        assert_eq!(buffer.read().unwrap().scale, 3);
+        assert_eq!(buffer.bind(2).kind(), DescriptorKind::UniformBuffer);
+        assert_eq!(buffer.bind(2).size(), 16);
+    }
+
+    #[test]
+    fn std140_size() {
+        let instance = Instance::new().unwrap();
+        assert!(matches!(
+            UniformBuffer::new(&instance, [1u32, 2, 3]),
+            Err(BufferError::NotStd140Sized(12))
+        ));
+        assert!(UniformBuffer::new(&instance, glam::Vec4::ONE).is_ok());
+    }
+
+    #[test]
+    fn run_program() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer Data { uint data[]; };
+            layout(binding = 1) uniform Parameters { uint scale; uint offset; } parameters;
+            void main() {
+                data[gl_GlobalInvocationID.x] *= parameters.scale;
+                data[gl_GlobalInvocationID.x] += parameters.offset;
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, &code, "test.glsl", "main").unwrap();
+        let data = CpuBuffer::from_vec(&instance, vec![1u32, 2, 3, 4]).unwrap();
+        let parameters = UniformBuffer::new(
+            &instance,
+            Parameters {
+                scale: 2,
+                offset: 1,
+                padding: [0; 2],
+            },
+        )
+        .unwrap();
+
+        let storage = CpuBuffer::from_vec(&instance, vec![2u32, 1]).unwrap();
+        assert!(matches!(
+            TaskBuilder::new(&instance).unwrap().run_program(
+                &program,
+                (4, 1, 1),
+                vec![data.bind(0), storage.bind(1)]
+            ),
+            Err(TaskError::BindingKindMismatch {
+                binding: 1,
+                expected: DescriptorKind::UniformBuffer,
+                found: DescriptorKind::StorageBuffer,
+                ..
+            })
+        ));
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(&program, (4, 1, 1), vec![data.bind(0), parameters.bind(1)])
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+        assert_eq!(data.read().unwrap(), vec![3, 5, 7, 9]);
+
+        let oversized = UniformBuffer::new(&instance, [parameters.read().unwrap(); 2]).unwrap();
+        assert!(matches!(
+            TaskBuilder::new(&instance).unwrap().run_program(
+                &program,
+                (4, 1, 1),
+                vec![data.bind(0), oversized.bind(1)]
+            ),
+            Err(TaskError::UniformSizeMismatch {
+                binding: 1,
+                expected: 16,
+                size: 32,
+                ..
+            })
+        ));
+    }
+}
@@ -2,20 +2,42 @@ pub use vulkano::{
     buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
     command_buffer::{
         allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
-        AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo, PrimaryAutoCommandBuffer,
+        AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferInfo, CopyBufferToImageInfo,
+        CopyImageToBufferInfo, DispatchIndirectCommand, PrimaryAutoCommandBuffer,
     },
     descriptor_set::{
-        allocator::StandardDescriptorSetAllocator, PersistentDescriptorSet, WriteDescriptorSet,
+        allocator::StandardDescriptorSetAllocator, layout::DescriptorType, PersistentDescriptorSet,
+        WriteDescriptorSet,
+    },
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType, SubgroupFeatures},
+        Device, DeviceCreateInfo, Queue, QueueCreateInfo, QueueFlags,
+    },
+    format::{Format, FormatFeatures},
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageType, ImageUsage,
+    },
+    instance::{
+        debug::{
+            DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger,
+            DebugUtilsMessengerCallback, DebugUtilsMessengerCreateInfo,
+        },
+        Instance, InstanceCreateInfo, InstanceExtensions,
+    },
+    memory::{
+        allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+        MemoryHeapFlags,
     },
-    device::{Device, DeviceCreateInfo, Queue, QueueCreateInfo, QueueFlags},
-    instance::{Instance, InstanceCreateInfo},
-    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
     pipeline::{
-        compute::ComputePipelineCreateInfo, layout::PipelineDescriptorSetLayoutCreateInfo,
+        cache::{PipelineCache, PipelineCacheCreateInfo},
+        compute::ComputePipelineCreateInfo,
+        layout::PipelineDescriptorSetLayoutCreateInfo,
         ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
         PipelineShaderStageCreateInfo,
     },
-    shader::{ShaderModule, ShaderModuleCreateInfo},
+    shader::{spirv, EntryPoint, ShaderModule, ShaderModuleCreateInfo, ShaderStages},
     sync::{self, GpuFuture},
-    DeviceSize, VulkanLibrary,
+    DeviceSize, Version, VulkanLibrary,
 };
@@ -0,0 +1,204 @@
+use super::*;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+type ProgramOptions = Box<dyn Fn(ProgramBuilder) -> ProgramBuilder>;
+
+/// Reloads a [`Program`] from a shader file whenever it or one of the files it includes changes
+/// on disk, by polling their modification times.
+///
+/// A failed reload keeps the previous program, so a typo while editing a shader doesn't stop
+/// the renderer.
+pub struct ProgramWatcher {
+    instance: Instance,
+    path: PathBuf,
+    entry_point: String,
+    options: ProgramOptions,
+    program: Program,
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    error: Option<ProgramError>,
+}
+
+impl ProgramWatcher {
+    pub fn new(
+        instance: &Instance,
+        path: impl Into<PathBuf>,
+        entry_point: &str,
+    ) -> Result<ProgramWatcher, ProgramError> {
+        Self::with_options(instance, path, entry_point, |builder| builder)
+    }
+
+    /// Like [`ProgramWatcher::new`], but applies `options` to the [`ProgramBuilder`] before every
+    /// build, to set defines or compile options.
+    pub fn with_options(
+        instance: &Instance,
+        path: impl Into<PathBuf>,
+        entry_point: &str,
+        options: impl Fn(ProgramBuilder) -> ProgramBuilder + 'static,
+    ) -> Result<ProgramWatcher, ProgramError> {
+        let path = path.into();
+        let program = options(ProgramBuilder::from_file(&path, entry_point)?).build(instance)?;
+
+        Ok(ProgramWatcher {
+            instance: instance.clone(),
+            modified: modification_times(&path, &program),
+            path,
+            entry_point: entry_point.to_string(),
+            options: Box::new(options),
+            program,
+            error: None,
+        })
+    }
+
+    /// The most recent program that compiled successfully.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The error of the last reload if it failed, cleared once a reload succeeds.
+    pub fn error(&self) -> Option<&ProgramError> {
+        self.error.as_ref()
+    }
+
+    /// Rebuilds the program if any watched file changed since the last call. Returns whether a
+    /// new program was swapped in, or the error of a failed rebuild. A failed rebuild is only
+    /// reported once, it is retried after the next change.
+    pub fn poll(&mut self) -> Result<bool, ProgramError> {
+        let changed = self
+            .modified
+            .iter()
+            .any(|(path, modified)| modified_time(path) != *modified);
+
+        if !changed {
+            return Ok(false);
+        }
+
+        match ProgramBuilder::from_file(&self.path, self.entry_point.as_str())
+            .and_then(|builder| (self.options)(builder).build(&self.instance))
+        {
+            Ok(program) => {
+                self.modified = modification_times(&self.path, &program);
+                self.program = program;
+                self.error = None;
+                Ok(true)
+            }
+            Err(e) => {
+                // keep watching the previous includes, the failed source may not list them all
+                for (path, modified) in &mut self.modified {
+                    *modified = modified_time(path);
+                }
+                self.error = Some(e.clone());
+                Err(e)
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn modification_times(path: &Path, program: &Program) -> Vec<(PathBuf, Option<SystemTime>)> {
+    std::iter::once(path)
+        .chain(program.included_files().iter().map(PathBuf::as_path))
+        .map(|path| (path.to_path_buf(), modified_time(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Writes `source` with a modification time `seconds` in the future, so consecutive writes
+    /// are detected regardless of the file system's timestamp resolution.
+    fn write_shader(path: &Path, source: &str, seconds: u64) {
+        std::fs::write(path, source).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    fn run(instance: &Instance, program: &Program) -> u32 {
+        let buffer = CpuBuffer::from_vec(instance, vec![0u32]).unwrap();
+        TaskBuilder::new(instance)
+            .unwrap()
+            .run_program(program, (1, 1, 1), vec![buffer.bind(0)])
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+        buffer.read().unwrap()[0]
+    }
+
+    #[test]
+    fn reload() {
+        let dir = std::env::temp_dir().join(format!(
+            "voxel_renderer_program_watcher_{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.glsl");
+        let include_path = dir.join("value.glsl");
+
+        let main = |expression: &str| {
+            format!(
+                "#version 460\n#include \"value.glsl\"\n\
+                 layout(binding = 0) buffer Data {{ uint data; }};\n\
+                 void main() {{ data = {}; }}\n",
+                expression
+            )
+        };
+
+        write_shader(&include_path, "const uint VALUE = 1;", 0);
+        write_shader(&path, &main("VALUE"), 0);
+
+        let instance = InstanceBuilder::new().no_cache().build().unwrap();
+        let mut watcher =
+            ProgramWatcher::with_options(&instance, &path, "main", |b| b.define("OFFSET", 10))
+                .unwrap();
+        assert_eq!(run(&instance, watcher.program()), 1);
+        assert_eq!(watcher.program().included_files(), [include_path.clone()]);
+        assert!(!watcher.poll().unwrap());
+
+        write_shader(&path, &main("VALUE + OFFSET"), 1);
+        assert!(watcher.poll().unwrap());
+        assert_eq!(run(&instance, watcher.program()), 11);
+
+        write_shader(&include_path, "const uint VALUE = 2;", 2);
+        assert!(watcher.poll().unwrap());
+        assert_eq!(run(&instance, watcher.program()), 12);
+
+        write_shader(&path, &main("VALUE +"), 3);
+        assert!(matches!(
+            watcher.poll(),
+            Err(ProgramError::CompilationFailed(_))
+        ));
+        assert!(watcher.error().is_some());
+        assert!(!watcher.poll().unwrap());
+        assert_eq!(run(&instance, watcher.program()), 12);
+
+        write_shader(&path, &main("VALUE * 3"), 4);
+        assert!(watcher.poll().unwrap());
+        assert!(watcher.error().is_none());
+        assert_eq!(run(&instance, watcher.program()), 6);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shader_directory() {
+        let instance = Instance::new().unwrap();
+        let mut watcher = ProgramWatcher::new(&instance, "shader/main.glsl", "main").unwrap();
+
+        assert_eq!(watcher.path(), Path::new("shader/main.glsl"));
+        assert_eq!(watcher.program().reflection().local_size, [64, 1, 1]);
+        assert!(!watcher.poll().unwrap());
+    }
+}
@@ -1,7 +1,37 @@
+mod cli;
 #[allow(unused_imports, dead_code)]
 mod engine;
+mod output;
 mod preamble;
 mod renderer;
+mod tone_map;
 mod world;
 
-fn main() {}
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let command = match cli::Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match command {
+        cli::Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+        cli::Command::Render(args) => cli::render(&args),
+        cli::Command::Compile(args) => cli::compile(&args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{}", cli::error_report(&error));
+            ExitCode::from(cli::exit_code(&error))
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use super::preamble::*;
+use super::tone_map::{tone_map, ToneMapping};
+use std::path::Path;
+
+/// Saves a linear HDR image as OpenEXR, keeping the full 32-bit float data.
+pub fn save_exr(image: &image::Rgba32FImage, path: impl AsRef<Path>) -> Result<()> {
+    image.save_with_format(path, image::ImageFormat::OpenExr)?;
+    Ok(())
+}
+
+/// Tone maps a linear HDR image with `tone_mapping` and saves it as an 8-bit PNG.
+pub fn save_png(
+    image: &image::Rgba32FImage,
+    path: impl AsRef<Path>,
+    tone_mapping: &ToneMapping,
+) -> Result<()> {
+    tone_map(image, tone_mapping).save_with_format(path, image::ImageFormat::Png)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tone_map::ToneMapOperator;
+
+    fn test_image() -> image::Rgba32FImage {
+        image::Rgba32FImage::from_fn(16, 8, |x, y| {
+            image::Rgba([x as f32 * 0.5, y as f32 * 0.25, 0.125, 1.0])
+        })
+    }
+
+    #[test]
+    fn exr_round_trip() {
+        let path = std::env::temp_dir().join("voxel_renderer_exr_round_trip.exr");
+        let image = test_image();
+
+        save_exr(&image, &path).unwrap();
+        let loaded = image::open(&path).unwrap().into_rgba32f();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, image);
+    }
+
+    #[test]
+    fn png_tone_mapped() {
+        let path = std::env::temp_dir().join("voxel_renderer_png_tone_mapped.png");
+        let image = test_image();
+
+        let tone_mapping = ToneMapOperator::Reinhard.into();
+
+        save_png(&image, &path, &tone_mapping).unwrap();
+        let loaded = image::open(&path).unwrap().into_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, tone_map(&image, &tone_mapping));
+    }
+}
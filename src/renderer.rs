@@ -1,50 +1,394 @@
 use super::preamble::*;
+use crate::tone_map::{ToneMapStage, ToneMapping};
+use crate::world::{Camera, MaterialProperties, SceneBuffer, SceneKind};
 use image;
 
+/// Built-in render shader that ray-marches a [`VoxelGrid`](crate::world::VoxelGrid) with a
+/// 3D-DDA traversal.
+///
+/// Expects the image at binding 0, the camera uniform at binding 1, the scene at binding 2 and the
+/// material table at binding 3, see [`Renderer::render_scene`].
+pub const VOXEL_SHADER: &str = concat!(
+    "#version 460\n",
+    "#include \"voxel.glsl\"\n",
+    "#include \"dda.glsl\"\n",
+);
+
+/// Built-in render shader that traces an [`Octree`](crate::world::Octree), with the same
+/// bindings as [`VOXEL_SHADER`].
+pub const OCTREE_SHADER: &str = concat!(
+    "#version 460\n",
+    "#include \"voxel.glsl\"\n",
+    "#include \"octree.glsl\"\n",
+);
+
+/// Built-in path tracer for a [`VoxelGrid`](crate::world::VoxelGrid) that adds its samples to an
+/// [`Accumulation`], see [`Renderer::accumulate`].
+pub const PATH_TRACE_SHADER: &str = concat!(
+    "#version 460\n",
+    "#include \"path_trace.glsl\"\n",
+    "#include \"dda.glsl\"\n",
+);
+
+#[derive(BufferContents, Copy, Clone)]
+#[repr(C)]
+struct FrameProperties {
+    seed: u32,
+    samples: u32,
+    accumulate: u32,
+}
+
+/// Running sum of path traced samples, kept on the GPU between [`Renderer::accumulate`] calls.
+pub struct Accumulation {
+    instance: Instance,
+    image_size: glam::UVec2,
+    buffer: GpuBuffer<f32>,
+    staging_buffer: CpuBuffer<f32>,
+    frames: u32,
+    samples_per_pixel: u32,
+}
+
+impl Accumulation {
+    pub fn new(instance: &Instance, image_size: glam::UVec2) -> Result<Accumulation> {
+        if image_size.x == 0 || image_size.y == 0 {
+            return Err(anyhow!("invalid image size"));
+        }
+        // checked here rather than in `Renderer::accumulate`, before allocating the buffers
+        check_dispatch_size(instance, image_size)?;
+
+        let len = 4 * image_size.x as usize * image_size.y as usize;
+        Ok(Accumulation {
+            instance: instance.clone(),
+            image_size,
+            buffer: GpuBuffer::new(instance, len)?,
+            staging_buffer: CpuBuffer::new(instance, len)?,
+            frames: 0,
+            samples_per_pixel: 0,
+        })
+    }
+
+    pub fn image_size(&self) -> glam::UVec2 {
+        self.image_size
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    /// Sum of radiance in rgb and the number of samples in alpha, per pixel.
+    pub fn buffer(&self) -> &GpuBuffer<f32> {
+        &self.buffer
+    }
+
+    /// Discards all samples, for example after the camera or scene changed. The next
+    /// [`Renderer::accumulate`] call overwrites the buffer instead of adding to it.
+    pub fn reset(&mut self) {
+        self.frames = 0;
+        self.samples_per_pixel = 0;
+    }
+
+    /// Mean of all samples accumulated so far, in linear HDR.
+    pub fn mean(&self) -> Result<image::Rgba32FImage> {
+        if self.samples_per_pixel == 0 {
+            return Err(anyhow!("no samples have been accumulated"));
+        }
+
+        TaskBuilder::new(&self.instance)?
+            .copy_buffer(&self.buffer, &self.staging_buffer)?
+            .build_submit_and_wait()?;
+
+        let mut data = self.staging_buffer.read()?;
+        for pixel in data.chunks_exact_mut(4) {
+            let samples = pixel[3];
+            pixel[..3].iter_mut().for_each(|c| *c /= samples);
+            pixel[3] = 1.0;
+        }
+
+        Ok(image::Rgba32FImage::from_raw(self.image_size.x, self.image_size.y, data).unwrap())
+    }
+}
+
 pub struct Renderer {
     instance: Instance,
     render_program: Program,
+    /// Scene the render program traces, `None` for programs that don't read a scene.
+    scene_kind: Option<SceneKind>,
+    tone_map_stage: ToneMapStage,
+    tone_mapping: ToneMapping,
 }
 
 impl Renderer {
     pub fn new(instance: Instance, render_shader: &str) -> Result<Renderer> {
         let render_program = Program::new(&instance, render_shader, "render.glsl", "main")?;
-        Ok(Renderer {
+        Self::from_program(instance, render_program)
+    }
+
+    /// Renders with an already built program, such as a variant of one of the built-in shaders
+    /// compiled with [`ProgramBuilder`].
+    pub fn from_program(instance: Instance, render_program: Program) -> Result<Renderer> {
+        let tone_map_stage = ToneMapStage::new(&instance)?;
+        Ok(Self::from_parts(instance, render_program, tone_map_stage))
+    }
+
+    /// Renders with an already built program and tone map stage, so nothing is compiled, for
+    /// example when both are loaded from SPIR-V.
+    pub fn from_parts(
+        instance: Instance,
+        render_program: Program,
+        tone_map_stage: ToneMapStage,
+    ) -> Renderer {
+        Renderer {
             instance,
             render_program,
-        })
+            scene_kind: None,
+            tone_map_stage,
+            tone_mapping: ToneMapping::default(),
+        }
+    }
+
+    pub fn voxel(instance: Instance) -> Result<Renderer> {
+        Ok(Self::new(instance, VOXEL_SHADER)?.for_scene(SceneKind::VoxelGrid))
+    }
+
+    pub fn octree(instance: Instance) -> Result<Renderer> {
+        Ok(Self::new(instance, OCTREE_SHADER)?.for_scene(SceneKind::Octree))
+    }
+
+    pub fn path_tracer(instance: Instance) -> Result<Renderer> {
+        Ok(Self::new(instance, PATH_TRACE_SHADER)?.for_scene(SceneKind::VoxelGrid))
+    }
+
+    /// Sets the kind of scene the render program traces. Renderers built with
+    /// [`Renderer::new`] or [`Renderer::from_program`] refuse to render a scene until it is set,
+    /// since a scene of another kind would be read as garbage.
+    pub fn for_scene(mut self, scene_kind: SceneKind) -> Self {
+        self.scene_kind = Some(scene_kind);
+        self
+    }
+
+    pub fn scene_kind(&self) -> Option<SceneKind> {
+        self.scene_kind
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.render_program
+    }
+
+    /// Replaces the render program, for example with the latest program of a
+    /// [`ProgramWatcher`] after [`ProgramWatcher::poll`] rebuilt it. The scene kind is kept.
+    pub fn set_program(&mut self, program: Program) {
+        self.render_program = program;
+    }
+
+    pub fn tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+
+    /// Sets the post-process applied by [`Renderer::render`] and [`Renderer::render_scene`].
+    /// Defaults to clamping the linear values.
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
     }
 
     pub fn render(&self, image_size: glam::UVec2) -> Result<image::RgbaImage> {
-        if image_size.x == 0 || image_size.y == 0 {
-            return Err(anyhow!("invalid image size"));
+        self.dispatch_tone_mapped(image_size, Vec::new())
+    }
+
+    /// Like [`Renderer::render`], but returns the linear float image without clamping it.
+    pub fn render_hdr(&self, image_size: glam::UVec2) -> Result<image::Rgba32FImage> {
+        self.dispatch(image_size, Vec::new())
+    }
+
+    pub fn render_scene(
+        &self,
+        camera: &Camera,
+        scene: &impl SceneBuffer,
+        materials: &[MaterialProperties],
+        image_size: glam::UVec2,
+    ) -> Result<image::RgbaImage> {
+        let bindings = self.scene_bindings(camera, scene, materials)?;
+        self.dispatch_tone_mapped(image_size, bindings)
+    }
+
+    pub fn render_scene_hdr(
+        &self,
+        camera: &Camera,
+        scene: &impl SceneBuffer,
+        materials: &[MaterialProperties],
+        image_size: glam::UVec2,
+    ) -> Result<image::Rgba32FImage> {
+        let bindings = self.scene_bindings(camera, scene, materials)?;
+        self.dispatch(image_size, bindings)
+    }
+
+    /// Adds `samples` path traced samples per pixel to `accumulation`, using a new seed for every
+    /// frame. The accumulation must have been created on the renderer's instance or a clone of it.
+    pub fn accumulate(
+        &self,
+        accumulation: &mut Accumulation,
+        camera: &Camera,
+        scene: &impl SceneBuffer,
+        materials: &[MaterialProperties],
+        samples: u32,
+    ) -> Result<()> {
+        if samples == 0 {
+            return Err(anyhow!("invalid sample count"));
+        }
+        if !self.instance.shares_device(&accumulation.instance) {
+            return Err(anyhow!(
+                "the accumulation was created on another instance than the renderer"
+            ));
         }
 
-        let image = CpuBuffer::<f32>::new(
-            &self.instance,
-            4 * image_size.x as usize * image_size.y as usize,
-        )?;
+        let frame = FrameProperties {
+            seed: accumulation.frames,
+            samples,
+            accumulate: (accumulation.samples_per_pixel > 0) as u32,
+        };
+
+        let mut bindings = self.scene_bindings(camera, scene, materials)?;
+        bindings.push(accumulation.buffer.bind(0));
 
         TaskBuilder::new(&self.instance)?
-            .run_program(
+            .run_program_with_push_constants(
                 &self.render_program,
-                (image_size.x as usize, image_size.y as usize, 1),
-                vec![image.bind(0)],
+                (
+                    accumulation.image_size.x as usize,
+                    accumulation.image_size.y as usize,
+                    1,
+                ),
+                bindings,
+                frame,
             )?
+            .build_submit_and_wait()?;
+
+        accumulation.frames += 1;
+        accumulation.samples_per_pixel += samples;
+
+        Ok(())
+    }
+
+    fn scene_bindings(
+        &self,
+        camera: &Camera,
+        scene: &impl SceneBuffer,
+        materials: &[MaterialProperties],
+    ) -> Result<Vec<BufferBinding>> {
+        match self.scene_kind {
+            Some(kind) if kind == scene.kind() => (),
+            Some(kind) => {
+                return Err(anyhow!(
+                    "the renderer traces a {:?} scene but a {:?} scene was given",
+                    kind,
+                    scene.kind()
+                ))
+            }
+            None => return Err(anyhow!("the renderer's scene kind is not set")),
+        }
+
+        let camera_buffer = UniformBuffer::new(&self.instance, camera.properties())?;
+        let scene_buffer = scene.upload(&self.instance)?;
+        let material_buffer = CpuBuffer::from_vec(&self.instance, materials.to_vec())?;
+
+        Ok(vec![
+            camera_buffer.bind(1),
+            scene_buffer.bind(2),
+            material_buffer.bind(3),
+        ])
+    }
+
+    fn dispatch(
+        &self,
+        image_size: glam::UVec2,
+        bindings: Vec<BufferBinding>,
+    ) -> Result<image::Rgba32FImage> {
+        let image = CpuBuffer::<f32>::new(&self.instance, image_len(image_size)?)?;
+
+        self.record_render(&image, image_size, bindings)?
             .build()?
             .submit()?
             .wait()?;
 
-        let image =
-            image::Rgba32FImage::from_raw(image_size.x, image_size.y, image.read()?).unwrap();
+        Ok(image::Rgba32FImage::from_raw(image_size.x, image_size.y, image.read()?).unwrap())
+    }
+
+    /// Renders into a GPU-only buffer and runs the tone map stage in the same task, so only the
+    /// display values are read back.
+    fn dispatch_tone_mapped(
+        &self,
+        image_size: glam::UVec2,
+        bindings: Vec<BufferBinding>,
+    ) -> Result<image::RgbaImage> {
+        let hdr_image = GpuBuffer::<f32>::new(&self.instance, image_len(image_size)?)?;
+        let ldr_image = CpuBuffer::<f32>::new(&self.instance, hdr_image.len())?;
+
+        let task = self.record_render(&hdr_image, image_size, bindings)?;
+        self.tone_map_stage
+            .record(
+                &self.instance,
+                task,
+                &self.tone_mapping,
+                &hdr_image,
+                &ldr_image,
+                image_size,
+            )?
+            .build_submit_and_wait()?;
 
-        Ok(image::DynamicImage::ImageRgba32F(image).into_rgba8())
+        let image = image::Rgba32FImage::from_raw(image_size.x, image_size.y, ldr_image.read()?);
+        Ok(image::DynamicImage::ImageRgba32F(image.unwrap()).into_rgba8())
+    }
+
+    fn record_render<Location>(
+        &self,
+        image: &Buffer<f32, Location>,
+        image_size: glam::UVec2,
+        bindings: Vec<BufferBinding>,
+    ) -> Result<TaskBuilder> {
+        check_dispatch_size(&self.instance, image_size)?;
+
+        let mut bindings = bindings;
+        bindings.push(image.bind(0));
+
+        Ok(TaskBuilder::new(&self.instance)?.run_program(
+            &self.render_program,
+            (image_size.x as usize, image_size.y as usize, 1),
+            bindings,
+        )?)
     }
 }
 
+/// Renders dispatch one work group per pixel, which the device limits in each dimension.
+fn check_dispatch_size(instance: &Instance, image_size: glam::UVec2) -> Result<()> {
+    let [max_x, max_y, _] = instance.device_info().max_compute_work_group_count;
+    if image_size.x > max_x || image_size.y > max_y {
+        return Err(anyhow!(
+            "image size {}x{} exceeds the device dispatch limit of {}x{}",
+            image_size.x,
+            image_size.y,
+            max_x,
+            max_y
+        ));
+    }
+    Ok(())
+}
+
+fn image_len(image_size: glam::UVec2) -> Result<usize> {
+    if image_size.x == 0 || image_size.y == 0 {
+        return Err(anyhow!("invalid image size"));
+    }
+
+    Ok(4 * image_size.x as usize * image_size.y as usize)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tone_map::{tone_map, ToneMapOperator};
+    use crate::world::{Octree, VoxelGrid};
 
     #[test]
     fn blank_image() {
@@ -101,4 +445,340 @@ mod tests {
 
         assert_eq!(reference_image, rendered_image);
     }
+
+    #[test]
+    fn included_grad_image() {
+        let code = r#"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            #include "image.glsl"
+            void main() {
+                image_store(vec4(vec2(image_pos()) / vec2(image_size()), 0.0, 1.0));
+            }
+        "#;
+
+        let reference_image = image::ImageReader::open("test_references/grad_image.png")
+            .unwrap()
+            .decode()
+            .unwrap()
+            .into_rgba8();
+
+        let instance = Instance::new().unwrap();
+        let rendered_image = Renderer::new(instance, code)
+            .unwrap()
+            .render(glam::UVec2::new(720, 480))
+            .unwrap();
+
+        assert_eq!(reference_image, rendered_image);
+    }
+
+    #[test]
+    fn watched_program() {
+        let path = std::env::temp_dir().join("voxel_renderer_watched_render.glsl");
+        let write_shader = |red: f32, seconds: u64| {
+            let source = format!(
+                "#version 460\n\
+                 layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;\n\
+                 layout(binding = 0) buffer Image {{ vec4 image[]; }};\n\
+                 void main() {{\n\
+                     uvec2 size = gl_NumWorkGroups.xy;\n\
+                     uvec2 pos = gl_GlobalInvocationID.xy;\n\
+                     image[pos.y * size.x + pos.x] = vec4({:.1}, 0.0, 0.0, 1.0);\n\
+                 }}\n",
+                red
+            );
+            std::fs::write(&path, source).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(
+                    std::time::SystemTime::now() + std::time::Duration::from_secs(seconds),
+                )
+                .unwrap();
+        };
+
+        write_shader(0.5, 0);
+        let instance = InstanceBuilder::new().no_cache().build().unwrap();
+        let mut watcher = ProgramWatcher::new(&instance, &path, "main").unwrap();
+        let mut renderer = Renderer::from_program(instance, watcher.program().clone()).unwrap();
+
+        let image_size = glam::uvec2(4, 2);
+        let image = renderer.render_hdr(image_size).unwrap();
+        assert!(image.pixels().all(|p| p.0 == [0.5, 0.0, 0.0, 1.0]));
+
+        write_shader(2.0, 1);
+        assert!(watcher.poll().unwrap());
+        renderer.set_program(watcher.program().clone());
+
+        let image = renderer.render_hdr(image_size).unwrap();
+        assert!(image.pixels().all(|p| p.0 == [2.0, 0.0, 0.0, 1.0]));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn hdr_image() {
+        let code = r#"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            #include "image.glsl"
+            void main() {
+                image_store(vec4(4.0, 0.5, -1.0, 1.0));
+            }
+        "#;
+
+        let instance = Instance::new().unwrap();
+        let renderer = Renderer::new(instance, code).unwrap();
+
+        let hdr_image = renderer.render_hdr(glam::UVec2::new(16, 8)).unwrap();
+        assert!(hdr_image.pixels().all(|p| p.0 == [4.0, 0.5, -1.0, 1.0]));
+
+        let ldr_image = renderer.render(glam::UVec2::new(16, 8)).unwrap();
+        assert!(ldr_image.pixels().all(|p| p.0 == [255, 128, 0, 255]));
+    }
+
+    #[test]
+    fn tone_mapped_image() {
+        let code = r#"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            #include "image.glsl"
+            void main() {
+                image_store(vec4(vec2(image_pos()) / 4.0, 0.5, 1.0));
+            }
+        "#;
+
+        let instance = Instance::new().unwrap();
+        let mut renderer = Renderer::new(instance, code).unwrap();
+        renderer.set_tone_mapping(ToneMapping {
+            exposure: 2.0,
+            operator: ToneMapOperator::Aces,
+            srgb: true,
+        });
+
+        let image_size = glam::UVec2::new(32, 16);
+        let expected = tone_map(
+            &renderer.render_hdr(image_size).unwrap(),
+            &renderer.tone_mapping(),
+        );
+        let rendered_image = renderer.render(image_size).unwrap();
+
+        assert!(rendered_image
+            .as_raw()
+            .iter()
+            .zip(expected.as_raw())
+            .all(|(a, b)| a.abs_diff(*b) <= 1));
+    }
+
+    #[test]
+    fn voxel_scene() {
+        let mut scene = VoxelGrid::new(glam::uvec3(4, 4, 4)).unwrap();
+        scene
+            .fill_box(glam::uvec3(0, 0, 0), glam::uvec3(4, 4, 4), 1)
+            .unwrap();
+
+        let materials = vec![
+            MaterialProperties::new(glam::Vec3::ZERO, glam::Vec4::ZERO),
+            MaterialProperties::new(glam::vec3(1.0, 0.0, 0.0), glam::Vec4::ZERO),
+        ];
+
+        let camera = Camera::new(
+            glam::vec3(2.0, 2.0, 10.0),
+            glam::Vec3::ZERO,
+            glam::vec2(1.0, 1.0),
+            1.0,
+        );
+
+        let instance = Instance::new().unwrap();
+        let rendered_image = Renderer::voxel(instance)
+            .unwrap()
+            .render_scene(&camera, &scene, &materials, glam::UVec2::new(32, 32))
+            .unwrap();
+
+        let center = rendered_image.get_pixel(16, 16);
+        assert!(center[0] > 0);
+        assert_eq!((center[1], center[2], center[3]), (0, 0, 255));
+
+        let corner = rendered_image.get_pixel(0, 0);
+        assert!(corner[2] > corner[0]);
+    }
+
+    #[test]
+    fn octree_matches_voxel_grid() {
+        let mut scene = VoxelGrid::new(glam::uvec3(24, 16, 20)).unwrap();
+        scene
+            .fill_box(glam::uvec3(0, 0, 0), glam::uvec3(24, 2, 20), 1)
+            .unwrap();
+        scene
+            .fill_box(glam::uvec3(4, 2, 5), glam::uvec3(9, 12, 8), 2)
+            .unwrap();
+        scene
+            .fill_box(glam::uvec3(15, 2, 3), glam::uvec3(20, 5, 17), 3)
+            .unwrap();
+        scene.set(glam::uvec3(12, 10, 10), 2).unwrap();
+
+        let materials = vec![
+            MaterialProperties::new(glam::Vec3::ZERO, glam::Vec4::ZERO),
+            MaterialProperties::new(glam::vec3(0.8, 0.8, 0.8), glam::Vec4::ZERO),
+            MaterialProperties::new(glam::vec3(1.0, 0.2, 0.2), glam::Vec4::ZERO),
+            MaterialProperties::new(glam::vec3(0.2, 0.2, 1.0), glam::Vec4::ZERO),
+        ];
+
+        let mut camera = Camera::new(
+            glam::vec3(-10.0, 25.0, 40.0),
+            glam::Vec3::ZERO,
+            glam::vec2(1.0, 0.75),
+            1.0,
+        );
+        camera.look_at(glam::vec3(12.0, 4.0, 10.0));
+
+        let instance = Instance::new().unwrap();
+        let image_size = glam::UVec2::new(160, 120);
+
+        let voxel_image = Renderer::voxel(instance.clone())
+            .unwrap()
+            .render_scene(&camera, &scene, &materials, image_size)
+            .unwrap();
+        let octree_image = Renderer::octree(instance)
+            .unwrap()
+            .render_scene(
+                &camera,
+                &Octree::from_grid(&scene).unwrap(),
+                &materials,
+                image_size,
+            )
+            .unwrap();
+
+        // The traversals step through the same cells but compute the ray parameter at cell
+        // boundaries differently, so a ray grazing an edge between two faces can round to
+        // either side. Every differing pixel must therefore lie on an edge of the voxel
+        // image and show a colour from its neighbourhood there, and such pixels stay rare.
+        let mut mismatches = 0;
+        for (x, y, pixel) in octree_image.enumerate_pixels() {
+            let expected = voxel_image.get_pixel(x, y);
+            if pixel == expected {
+                continue;
+            }
+            mismatches += 1;
+
+            let neighbours: Vec<_> = (x.saturating_sub(1)..(x + 2).min(image_size.x))
+                .flat_map(|nx| {
+                    (y.saturating_sub(1)..(y + 2).min(image_size.y)).map(move |ny| (nx, ny))
+                })
+                .map(|(nx, ny)| voxel_image.get_pixel(nx, ny))
+                .collect();
+            assert!(
+                neighbours.iter().any(|n| *n != expected) && neighbours.contains(&pixel),
+                "pixel ({}, {}) differs away from an edge",
+                x,
+                y
+            );
+        }
+        assert!(mismatches * 100 < (image_size.x * image_size.y) as usize);
+    }
+
+    #[test]
+    fn scene_kind() {
+        let scene = VoxelGrid::new(glam::uvec3(2, 2, 2)).unwrap();
+        let octree = Octree::from_grid(&scene).unwrap();
+        let camera = Camera::new(
+            glam::vec3(1.0, 1.0, 5.0),
+            glam::Vec3::ZERO,
+            glam::vec2(1.0, 1.0),
+            1.0,
+        );
+        let materials = vec![MaterialProperties::new(glam::Vec3::ZERO, glam::Vec4::ZERO)];
+        let image_size = glam::uvec2(4, 4);
+
+        let instance = Instance::new().unwrap();
+        let renderer = Renderer::voxel(instance.clone()).unwrap();
+        assert_eq!(renderer.scene_kind(), Some(SceneKind::VoxelGrid));
+        assert!(renderer
+            .render_scene(&camera, &octree, &materials, image_size)
+            .is_err());
+        assert!(Renderer::octree(instance.clone())
+            .unwrap()
+            .render_scene(&camera, &scene, &materials, image_size)
+            .is_err());
+
+        let renderer = Renderer::new(instance, VOXEL_SHADER).unwrap();
+        assert!(renderer
+            .render_scene(&camera, &scene, &materials, image_size)
+            .is_err());
+        assert!(renderer
+            .for_scene(SceneKind::VoxelGrid)
+            .render_scene(&camera, &scene, &materials, image_size)
+            .is_ok());
+    }
+
+    #[test]
+    fn progressive_accumulation() {
+        let mut scene = VoxelGrid::new(glam::uvec3(8, 8, 8)).unwrap();
+        scene
+            .fill_box(glam::uvec3(0, 0, 0), glam::uvec3(8, 2, 8), 1)
+            .unwrap();
+        scene
+            .fill_box(glam::uvec3(3, 2, 3), glam::uvec3(5, 4, 5), 2)
+            .unwrap();
+
+        let materials = vec![
+            MaterialProperties::new(glam::Vec3::ZERO, glam::Vec4::ZERO),
+            MaterialProperties::new(glam::vec3(0.5, 0.5, 0.5), glam::Vec4::ZERO),
+            MaterialProperties::new(glam::vec3(1.0, 1.0, 1.0), glam::vec4(2.0, 0.0, 0.0, 0.0)),
+        ];
+
+        let mut camera = Camera::new(
+            glam::vec3(4.0, 8.0, 16.0),
+            glam::Vec3::ZERO,
+            glam::vec2(1.0, 1.0),
+            1.0,
+        );
+        camera.look_at(glam::vec3(4.0, 2.0, 4.0));
+
+        let instance = Instance::new().unwrap();
+        let renderer = Renderer::path_tracer(instance.clone()).unwrap();
+        let mut accumulation = Accumulation::new(&instance, glam::uvec2(24, 24)).unwrap();
+
+        assert!(accumulation.mean().is_err());
+
+        renderer
+            .accumulate(&mut accumulation, &camera, &scene, &materials, 2)
+            .unwrap();
+        let first = accumulation.mean().unwrap();
+
+        renderer
+            .accumulate(&mut accumulation, &camera, &scene, &materials, 2)
+            .unwrap();
+        let second = accumulation.mean().unwrap();
+
+        assert_eq!(accumulation.frames(), 2);
+        assert_eq!(accumulation.samples_per_pixel(), 4);
+        assert!(second
+            .pixels()
+            .all(|p| p.0.iter().all(|c| c.is_finite() && *c >= 0.0)));
+        assert_ne!(first, second);
+
+        // the emissive block in the middle of the image is brighter than anything around it
+        let center = second.get_pixel(12, 12);
+        assert!(center[0] >= 2.0 && center[3] == 1.0);
+
+        accumulation.reset();
+        assert_eq!(accumulation.samples_per_pixel(), 0);
+
+        renderer
+            .accumulate(&mut accumulation, &camera, &scene, &materials, 2)
+            .unwrap();
+        assert_eq!(accumulation.mean().unwrap(), first);
+
+        let other_instance = Instance::new().unwrap();
+        let mut other = Accumulation::new(&other_instance, glam::uvec2(24, 24)).unwrap();
+        assert!(renderer
+            .accumulate(&mut other, &camera, &scene, &materials, 2)
+            .is_err());
+        assert_eq!(other.samples_per_pixel(), 0);
+
+        let [max_x, _, _] = instance.device_info().max_compute_work_group_count;
+        assert!(Accumulation::new(&instance, glam::uvec2(max_x + 1, 1)).is_err());
+    }
 }
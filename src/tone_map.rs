@@ -0,0 +1,347 @@
+use super::preamble::*;
+
+/// Built-in post-process shader applying a [`ToneMapping`], see [`ToneMapStage`].
+pub const TONE_MAP_SHADER: &str = include_str!("../shader/tone_map.glsl");
+
+/// Curve used to compress linear HDR colours into the displayable `[0, 1]` range.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// Clamps each channel, discarding everything above 1.
+    #[default]
+    Clamp,
+    /// `c / (1 + c)` (Reinhard et al., "Photographic Tone Reproduction for Digital Images").
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic reference curve.
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2, with a white point of 11.2.
+    Filmic,
+}
+
+impl ToneMapOperator {
+    pub fn apply(&self, color: glam::Vec3) -> glam::Vec3 {
+        let color = color.max(glam::Vec3::ZERO);
+
+        match self {
+            ToneMapOperator::Clamp => color,
+            ToneMapOperator::Reinhard => color / (1.0 + color),
+            ToneMapOperator::Aces => {
+                (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14)
+            }
+            ToneMapOperator::Filmic => {
+                filmic_curve(2.0 * color) / filmic_curve(glam::Vec3::splat(11.2))
+            }
+        }
+        .clamp(glam::Vec3::ZERO, glam::Vec3::ONE)
+    }
+}
+
+/// Conversion from linear HDR colour to display values: scale by `exposure`, compress with
+/// `operator`, then optionally encode with the sRGB transfer function.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToneMapping {
+    pub exposure: f32,
+    pub operator: ToneMapOperator,
+    pub srgb: bool,
+}
+
+impl Default for ToneMapping {
+    /// Clamps linear values without any other change.
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            operator: ToneMapOperator::Clamp,
+            srgb: false,
+        }
+    }
+}
+
+impl From<ToneMapOperator> for ToneMapping {
+    /// Applies `operator` and sRGB encoding at unit exposure.
+    fn from(operator: ToneMapOperator) -> Self {
+        Self {
+            exposure: 1.0,
+            operator,
+            srgb: true,
+        }
+    }
+}
+
+impl ToneMapping {
+    pub fn apply(&self, color: glam::Vec3) -> glam::Vec3 {
+        let color = self.operator.apply(color * self.exposure);
+
+        if self.srgb {
+            linear_to_srgb(color)
+        } else {
+            color
+        }
+    }
+
+    fn properties(&self) -> ToneMapProperties {
+        ToneMapProperties {
+            exposure: self.exposure,
+            operator: self.operator as u32,
+            srgb: self.srgb as u32,
+        }
+    }
+}
+
+#[derive(BufferContents, Copy, Clone)]
+#[repr(C)]
+struct ToneMapProperties {
+    exposure: f32,
+    operator: u32,
+    srgb: u32,
+}
+
+/// GPU post-process pass applying a [`ToneMapping`] to a buffer of linear `vec4` pixels.
+pub struct ToneMapStage {
+    program: Program,
+}
+
+impl ToneMapStage {
+    pub fn new(instance: &Instance) -> Result<ToneMapStage> {
+        Ok(Self::from_program(Program::new(
+            instance,
+            TONE_MAP_SHADER,
+            "tone_map.glsl",
+            "main",
+        )?))
+    }
+
+    /// Uses an already built [`TONE_MAP_SHADER`], such as one loaded from SPIR-V.
+    pub fn from_program(program: Program) -> ToneMapStage {
+        ToneMapStage { program }
+    }
+
+    /// Appends the pass to `task`, reading `hdr` and writing display values in `[0, 1]` to `ldr`.
+    pub fn record<InputLocation, OutputLocation>(
+        &self,
+        instance: &Instance,
+        task: TaskBuilder,
+        tone_mapping: &ToneMapping,
+        hdr: &Buffer<f32, InputLocation>,
+        ldr: &Buffer<f32, OutputLocation>,
+        image_size: glam::UVec2,
+    ) -> Result<TaskBuilder> {
+        let len = 4 * image_size.x as usize * image_size.y as usize;
+        if len == 0 || hdr.len() != len || ldr.len() != len {
+            return Err(anyhow!("tone map buffers do not match the image size"));
+        }
+
+        let properties = CpuBuffer::from_vec(instance, vec![tone_mapping.properties()])?;
+
+        Ok(task.run_program(
+            &self.program,
+            (image_size.x as usize, image_size.y as usize, 1),
+            vec![hdr.bind(0), ldr.bind(1), properties.bind(2)],
+        )?)
+    }
+}
+
+/// Converts a linear HDR image into 8-bit RGBA on the CPU, keeping alpha.
+pub fn tone_map(image: &image::Rgba32FImage, tone_mapping: &ToneMapping) -> image::RgbaImage {
+    let mut image = image.clone();
+
+    for pixel in image.pixels_mut() {
+        let [r, g, b, a] = pixel.0;
+        let color = tone_mapping.apply(glam::vec3(r, g, b));
+        pixel.0 = [color.x, color.y, color.z, a.clamp(0.0, 1.0)];
+    }
+
+    image::DynamicImage::ImageRgba32F(image).into_rgba8()
+}
+
+fn filmic_curve(x: glam::Vec3) -> glam::Vec3 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+fn linear_to_srgb(color: glam::Vec3) -> glam::Vec3 {
+    let f = |c: f32| {
+        if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    };
+    glam::vec3(f(color.x), f(color.y), f(color.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_image() -> image::Rgba32FImage {
+        image::Rgba32FImage::from_fn(64, 32, |x, y| {
+            let color = x as f32 / 8.0 * glam::vec3(1.0, y as f32 / 32.0, 0.25);
+            image::Rgba([color.x, color.y, color.z, 1.0])
+        })
+    }
+
+    fn tone_map_gpu(image: &image::Rgba32FImage, tone_mapping: &ToneMapping) -> image::RgbaImage {
+        let instance = Instance::new().unwrap();
+        let stage = ToneMapStage::new(&instance).unwrap();
+
+        let hdr = CpuBuffer::from_vec(&instance, image.as_raw().clone()).unwrap();
+        let ldr = CpuBuffer::<f32>::new(&instance, hdr.len()).unwrap();
+
+        stage
+            .record(
+                &instance,
+                TaskBuilder::new(&instance).unwrap(),
+                tone_mapping,
+                &hdr,
+                &ldr,
+                glam::uvec2(image.width(), image.height()),
+            )
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        let ldr = image::Rgba32FImage::from_raw(image.width(), image.height(), ldr.read().unwrap());
+        image::DynamicImage::ImageRgba32F(ldr.unwrap()).into_rgba8()
+    }
+
+    fn assert_matches_reference(tone_mapping: ToneMapping, reference: &str) {
+        let reference_image = image::ImageReader::open(reference)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .into_rgba8();
+
+        // GPU transcendental functions are allowed to differ from the CPU in the last few bits
+        let close = |a: &image::RgbaImage, b: &image::RgbaImage| {
+            a.dimensions() == b.dimensions()
+                && a.as_raw()
+                    .iter()
+                    .zip(b.as_raw())
+                    .all(|(a, b)| a.abs_diff(*b) <= 1)
+        };
+
+        assert!(close(
+            &tone_map(&test_image(), &tone_mapping),
+            &reference_image
+        ));
+        assert!(close(
+            &tone_map_gpu(&test_image(), &tone_mapping),
+            &reference_image
+        ));
+    }
+
+    #[test]
+    fn operators() {
+        let clamp = ToneMapOperator::Clamp;
+        assert_eq!(
+            clamp.apply(glam::vec3(-1.0, 0.5, 2.0)),
+            glam::vec3(0.0, 0.5, 1.0)
+        );
+
+        let reinhard = ToneMapOperator::Reinhard;
+        assert_eq!(
+            reinhard.apply(glam::vec3(0.0, 1.0, 3.0)),
+            glam::vec3(0.0, 0.5, 0.75)
+        );
+
+        for op in [ToneMapOperator::Aces, ToneMapOperator::Filmic] {
+            assert!(op
+                .apply(glam::Vec3::ZERO)
+                .abs_diff_eq(glam::Vec3::ZERO, 1e-6));
+            assert_eq!(op.apply(glam::Vec3::splat(100.0)), glam::Vec3::ONE);
+
+            let values: Vec<f32> = (0..100)
+                .map(|i| op.apply(glam::Vec3::splat(i as f32 * 0.1)).x)
+                .collect();
+            assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        }
+    }
+
+    #[test]
+    fn exposure_and_srgb() {
+        let tone_mapping = ToneMapping {
+            exposure: 0.5,
+            operator: ToneMapOperator::Clamp,
+            srgb: false,
+        };
+        assert_eq!(
+            tone_mapping.apply(glam::vec3(1.0, 2.0, 4.0)),
+            glam::vec3(0.5, 1.0, 1.0)
+        );
+
+        let tone_mapping = ToneMapping {
+            srgb: true,
+            ..tone_mapping
+        };
+        let color = tone_mapping.apply(glam::vec3(0.0, 0.004, 0.428));
+        assert!(color.abs_diff_eq(glam::vec3(0.0, 0.02584, 0.5), 1e-4));
+    }
+
+    #[test]
+    fn cpu_image() {
+        let hdr = image::Rgba32FImage::from_raw(2, 1, vec![0.5, 1.0, 4.0, 1.0, 1.0, 0.0, 0.0, 0.5])
+            .unwrap();
+
+        let ldr = tone_map(&hdr, &ToneMapping::default());
+        assert_eq!(ldr.get_pixel(0, 0).0, [128, 255, 255, 255]);
+        assert_eq!(ldr.get_pixel(1, 0).0, [255, 0, 0, 128]);
+
+        let ldr = tone_map(
+            &hdr,
+            &ToneMapping {
+                operator: ToneMapOperator::Reinhard,
+                ..Default::default()
+            },
+        );
+        assert_eq!(ldr.get_pixel(0, 0).0, [85, 128, 204, 255]);
+    }
+
+    #[test]
+    fn identity_on_gpu() {
+        let image = test_image();
+        assert_eq!(
+            tone_map_gpu(&image, &ToneMapping::default()),
+            tone_map(&image, &ToneMapping::default())
+        );
+    }
+
+    #[test]
+    fn reinhard_reference() {
+        assert_matches_reference(
+            ToneMapOperator::Reinhard.into(),
+            "test_references/tone_map_reinhard.png",
+        );
+    }
+
+    #[test]
+    fn aces_reference() {
+        assert_matches_reference(
+            ToneMapOperator::Aces.into(),
+            "test_references/tone_map_aces.png",
+        );
+    }
+
+    #[test]
+    fn filmic_reference() {
+        assert_matches_reference(
+            ToneMapOperator::Filmic.into(),
+            "test_references/tone_map_filmic.png",
+        );
+    }
+
+    #[test]
+    fn exposure_reference() {
+        assert_matches_reference(
+            ToneMapping {
+                exposure: 0.25,
+                operator: ToneMapOperator::Clamp,
+                srgb: true,
+            },
+            "test_references/tone_map_exposure.png",
+        );
+    }
+}
@@ -2,13 +2,14 @@ use crate::preamble::*;
 
 #[derive(BufferContents, Copy, Clone)]
 #[repr(C)]
-struct CameraProperties {
+pub struct CameraProperties {
     pub pos: glam::Vec3,
     padding_1: u32,
     pub rot: glam::Vec3,
     padding_2: u32,
     pub sensor_size: glam::Vec2,
     pub focal_distance: f32,
+    padding_3: u32,
 }
 
 impl CameraProperties {
@@ -25,6 +26,94 @@ impl CameraProperties {
             padding_2: 0,
             sensor_size,
             focal_distance,
+            padding_3: 0,
+        }
+    }
+}
+
+/// Pinhole camera looking down -z in its local space, with y up.
+///
+/// `rot` holds Euler angles in radians, applied as yaw (y), then pitch (x), then roll (z).
+/// This matches `camera_rotation` and `camera_ray` in `shader/camera.glsl`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub pos: glam::Vec3,
+    pub rot: glam::Vec3,
+    pub sensor_size: glam::Vec2,
+    pub focal_distance: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: glam::Vec3,
+    pub direction: glam::Vec3,
+}
+
+impl Camera {
+    pub fn new(
+        pos: glam::Vec3,
+        rot: glam::Vec3,
+        sensor_size: glam::Vec2,
+        focal_distance: f32,
+    ) -> Self {
+        Self {
+            pos,
+            rot,
+            sensor_size,
+            focal_distance,
+        }
+    }
+
+    pub fn properties(&self) -> CameraProperties {
+        CameraProperties::new(self.pos, self.rot, self.sensor_size, self.focal_distance)
+    }
+
+    pub fn rotation(&self) -> glam::Mat3 {
+        glam::Mat3::from_rotation_y(self.rot.y)
+            * glam::Mat3::from_rotation_x(self.rot.x)
+            * glam::Mat3::from_rotation_z(self.rot.z)
+    }
+
+    pub fn forward(&self) -> glam::Vec3 {
+        self.rotation() * glam::Vec3::NEG_Z
+    }
+
+    /// World to camera space transform.
+    pub fn view_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_mat3(self.rotation().transpose()) * glam::Mat4::from_translation(-self.pos)
+    }
+
+    /// Right-handed perspective projection with a `[0, 1]` depth range, matching the field of
+    /// view of the sensor.
+    pub fn projection_matrix(&self, near: f32, far: f32) -> glam::Mat4 {
+        let fov_y = 2.0 * (0.5 * self.sensor_size.y / self.focal_distance).atan();
+        let aspect_ratio = self.sensor_size.x / self.sensor_size.y;
+        glam::Mat4::perspective_rh(fov_y, aspect_ratio, near, far)
+    }
+
+    /// Points the camera at `target`, resetting its roll. Does nothing if `target` is the
+    /// camera position.
+    pub fn look_at(&mut self, target: glam::Vec3) {
+        let dir = (target - self.pos).normalize_or_zero();
+        if dir == glam::Vec3::ZERO {
+            return;
+        }
+
+        self.rot = glam::vec3(dir.y.asin(), (-dir.x).atan2(-dir.z), 0.0);
+    }
+
+    /// Primary ray through the center of `pixel`, where pixel `(0, 0)` is the top left corner.
+    pub fn ray(&self, pixel: glam::UVec2, image_size: glam::UVec2) -> Ray {
+        let uv = (pixel.as_vec2() + 0.5) / image_size.as_vec2() - 0.5;
+        let sensor_pos = glam::vec3(
+            uv.x * self.sensor_size.x,
+            -uv.y * self.sensor_size.y,
+            -self.focal_distance,
+        );
+
+        Ray {
+            origin: self.pos,
+            direction: (self.rotation() * sensor_pos).normalize(),
         }
     }
 }
@@ -51,7 +140,7 @@ mod tests {
         ";
 
         let instance = Instance::new().unwrap();
-        let program = Program::new(&instance, &code, "test", "main").unwrap();
+        let program = Program::new(&instance, code, "test", "main").unwrap();
 
         let camera = CameraProperties::new(
             glam::vec3(0.0, 0.0, 0.0),
@@ -76,4 +165,153 @@ mod tests {
         assert_eq!(camera.sensor_size, glam::vec2(7.0, 8.0));
         assert_eq!(camera.focal_distance, 9.0);
     }
+
+    #[test]
+    fn uniform_alignment() {
+        let code = r"
+            #version 460
+            struct Camera { vec3 pos; vec3 rot; vec2 sensor_size; float focal_distance; };
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) uniform buffer_1 { Camera camera; };
+            layout(binding = 1) buffer buffer_2 { float values[]; };
+            void main() {
+                values[0] = camera.pos.x;
+                values[1] = camera.pos.y;
+                values[2] = camera.pos.z;
+                values[3] = camera.rot.x;
+                values[4] = camera.rot.y;
+                values[5] = camera.rot.z;
+                values[6] = camera.sensor_size.x;
+                values[7] = camera.sensor_size.y;
+                values[8] = camera.focal_distance;
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, code, "test", "main").unwrap();
+
+        let camera = CameraProperties::new(
+            glam::vec3(1.0, 2.0, 3.0),
+            glam::vec3(4.0, 5.0, 6.0),
+            glam::vec2(7.0, 8.0),
+            9.0,
+        );
+
+        let camera_buffer = UniformBuffer::new(&instance, camera).unwrap();
+        let values_buffer = CpuBuffer::<f32>::new(&instance, 9).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(
+                &program,
+                (1, 1, 1),
+                vec![camera_buffer.bind(0), values_buffer.bind(1)],
+            )
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        assert_eq!(
+            values_buffer.read().unwrap(),
+            vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]
+        );
+    }
+
+    #[test]
+    fn look_at() {
+        let target = glam::vec3(3.0, -2.0, 5.0);
+        let mut camera = Camera::new(
+            glam::vec3(1.0, 2.0, 3.0),
+            glam::vec3(0.3, 0.2, 0.1),
+            glam::vec2(1.0, 1.0),
+            1.0,
+        );
+        camera.look_at(target);
+
+        let dir = (target - camera.pos).normalize();
+        assert!(camera.forward().abs_diff_eq(dir, 1e-5));
+        assert_eq!(camera.rot.z, 0.0);
+
+        let target_view = camera.view_matrix().transform_point3(target);
+        let distance = (target - camera.pos).length();
+        assert!(target_view.abs_diff_eq(glam::vec3(0.0, 0.0, -distance), 1e-5));
+    }
+
+    #[test]
+    fn projection() {
+        let camera = Camera::new(
+            glam::Vec3::ZERO,
+            glam::Vec3::ZERO,
+            glam::vec2(2.0, 1.0),
+            1.0,
+        );
+
+        let corner = camera
+            .projection_matrix(0.1, 100.0)
+            .project_point3(glam::vec3(1.0, 0.5, -1.0));
+
+        assert!((corner.x - 1.0).abs() < 1e-5);
+        assert!((corner.y - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn ray_matches_glsl() {
+        let code = r#"
+            #version 460
+            #include "camera.glsl"
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer buffer_1 { Camera camera; };
+            layout(binding = 1) buffer buffer_2 { vec4 rays[]; };
+            uvec2 pos = gl_GlobalInvocationID.xy;
+            uvec2 size = gl_NumWorkGroups.xy * gl_WorkGroupSize.xy;
+            void main() {
+                Ray ray = camera_ray(camera, pos, size);
+                uint idx = 2 * (pos.y * size.x + pos.x);
+                rays[idx] = vec4(ray.origin, 0.0);
+                rays[idx + 1] = vec4(ray.dir, 0.0);
+            }
+        "#;
+
+        let image_size = glam::uvec2(7, 5);
+        let mut camera = Camera::new(
+            glam::vec3(1.0, -2.0, 3.0),
+            glam::vec3(0.4, -1.2, 0.3),
+            glam::vec2(0.036, 0.024),
+            0.05,
+        );
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, code, "test", "main").unwrap();
+
+        for _ in 0..2 {
+            let camera_buffer = Buffer::from_vec(&instance, vec![camera.properties()]).unwrap();
+            let ray_buffer =
+                CpuBuffer::<glam::Vec4>::new(&instance, 2 * (image_size.x * image_size.y) as usize)
+                    .unwrap();
+
+            TaskBuilder::new(&instance)
+                .unwrap()
+                .run_program(
+                    &program,
+                    (image_size.x as usize, image_size.y as usize, 1),
+                    vec![camera_buffer.bind(0), ray_buffer.bind(1)],
+                )
+                .unwrap()
+                .build_submit_and_wait()
+                .unwrap();
+
+            let rays = ray_buffer.read().unwrap();
+
+            for y in 0..image_size.y {
+                for x in 0..image_size.x {
+                    let idx = 2 * (y * image_size.x + x) as usize;
+                    let ray = camera.ray(glam::uvec2(x, y), image_size);
+                    assert!(rays[idx].truncate().abs_diff_eq(ray.origin, 1e-3));
+                    assert!(rays[idx + 1].truncate().abs_diff_eq(ray.direction, 1e-3));
+                }
+            }
+
+            camera.look_at(glam::vec3(-4.0, 0.0, 2.0));
+        }
+    }
 }
@@ -1,8 +1,9 @@
 use crate::preamble::*;
 
+/// Material table entry; `properties.x` is the emission strength used by the path tracer.
 #[derive(BufferContents, Copy, Clone)]
 #[repr(C)]
-struct MaterialProperties {
+pub struct MaterialProperties {
     pub color: glam::Vec3,
     padding_1: u32,
     pub properties: glam::Vec4,
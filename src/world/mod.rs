@@ -1,4 +1,11 @@
-mod buffer_object;
 mod camera;
 mod material;
+mod octree;
 mod scene;
+mod vox;
+
+pub use camera::{Camera, CameraProperties, Ray};
+pub use material::MaterialProperties;
+pub use octree::Octree;
+pub use scene::{SceneBuffer, SceneKind, SceneProperties, VoxelGrid};
+pub use vox::{VoxError, VoxScene};
@@ -0,0 +1,251 @@
+use super::{SceneBuffer, SceneKind, SceneProperties, VoxelGrid};
+use crate::preamble::*;
+
+/// Sparse voxel octree built from a [`VoxelGrid`], stored as a flat array of nodes.
+///
+/// Every node is 8 consecutive words, one per child octant (`x | y << 1 | z << 2`). A child
+/// word of `0` is empty space, [`Octree::LEAF`]` | material` is a region filled with a single
+/// material, and any other value is the index of the child node. The root is node 0 and covers
+/// `root_size()` voxels along each axis, which `shader/octree.glsl` traverses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Octree {
+    size: glam::UVec3,
+    root_size: u32,
+    nodes: Vec<u32>,
+}
+
+impl Octree {
+    pub const LEAF: u32 = 1 << 31;
+
+    pub fn from_grid(grid: &VoxelGrid) -> Result<Octree> {
+        let root_size = grid.size().max_element().next_power_of_two().max(2);
+
+        let mut octree = Octree {
+            size: grid.size(),
+            root_size,
+            nodes: Vec::new(),
+        };
+
+        let root = octree.build(grid, glam::UVec3::ZERO, root_size)?;
+        if octree.nodes.is_empty() {
+            octree.nodes = vec![root; 8];
+        }
+
+        Ok(octree)
+    }
+
+    pub fn size(&self) -> glam::UVec3 {
+        self.size
+    }
+
+    pub fn root_size(&self) -> u32 {
+        self.root_size
+    }
+
+    pub fn nodes(&self) -> &[u32] {
+        &self.nodes
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() / 8
+    }
+
+    /// Size in bytes of the uploaded scene buffer.
+    pub fn memory_size(&self) -> usize {
+        (SceneProperties::LEN + self.nodes.len()) * std::mem::size_of::<u32>()
+    }
+
+    pub fn get(&self, pos: glam::UVec3) -> Option<u32> {
+        if !pos.cmplt(self.size).all() {
+            return None;
+        }
+
+        let mut word = 0;
+        let mut region_size = self.root_size;
+
+        while region_size > 1 {
+            region_size >>= 1;
+            let octant = (pos & region_size).cmpne(glam::UVec3::ZERO).bitmask();
+            word = self.nodes[(word * 8 + octant) as usize];
+
+            if word == 0 || word & Self::LEAF != 0 {
+                break;
+            }
+        }
+
+        Some(word & !Self::LEAF)
+    }
+
+    /// Builds the region of `size` voxels starting at `min`, returning the word its parent stores
+    /// for it. Regions of a single material collapse into leaves instead of allocating a node.
+    fn build(&mut self, grid: &VoxelGrid, min: glam::UVec3, size: u32) -> Result<u32> {
+        if !min.cmplt(grid.size()).all() {
+            return Ok(0);
+        }
+
+        if size == 1 {
+            return match grid.get(min).unwrap() {
+                VoxelGrid::EMPTY => Ok(0),
+                material if material < Self::LEAF => Ok(Self::LEAF | material),
+                _ => Err(anyhow!("material index is too large for an octree")),
+            };
+        }
+
+        let index = self.nodes.len();
+        self.nodes.extend([0; 8]);
+
+        let half = size / 2;
+        for octant in 0..8 {
+            let offset = glam::uvec3(octant & 1, (octant >> 1) & 1, (octant >> 2) & 1) * half;
+            let word = self.build(grid, min + offset, half)?;
+            self.nodes[index + octant as usize] = word;
+        }
+
+        // children that are leaves or empty never allocate nodes, so a uniform node is always
+        // the last one and can be removed again
+        let children = &self.nodes[index..index + 8];
+        let uniform = children.iter().all(|&word| word == children[0]);
+        if uniform && (children[0] == 0 || children[0] & Self::LEAF != 0) {
+            let word = children[0];
+            self.nodes.truncate(index);
+            return Ok(word);
+        }
+
+        Ok((index / 8) as u32)
+    }
+}
+
+impl SceneBuffer for Octree {
+    fn kind(&self) -> SceneKind {
+        SceneKind::Octree
+    }
+
+    fn properties(&self) -> SceneProperties {
+        SceneProperties::new(self.size)
+    }
+
+    fn data(&self) -> &[u32] {
+        &self.nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_grid(size: glam::UVec3) -> VoxelGrid {
+        let mut grid = VoxelGrid::new(size).unwrap();
+        let mut state = 12345u32;
+
+        for z in 0..size.z {
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                    if state >> 28 == 0 {
+                        grid.set(glam::uvec3(x, y, z), 1 + (state >> 8) % 3)
+                            .unwrap();
+                    }
+                }
+            }
+        }
+
+        grid.fill_box(glam::UVec3::ZERO, (size / 2).max(glam::UVec3::ONE), 4)
+            .unwrap();
+        grid
+    }
+
+    #[test]
+    fn equivalence() {
+        for size in [
+            glam::uvec3(1, 1, 1),
+            glam::uvec3(8, 8, 8),
+            glam::uvec3(13, 7, 9),
+        ] {
+            let grid = test_grid(size);
+            let octree = Octree::from_grid(&grid).unwrap();
+
+            assert_eq!(octree.size(), size);
+            assert!(octree.root_size() >= size.max_element());
+
+            for z in 0..size.z + 1 {
+                for y in 0..size.y + 1 {
+                    for x in 0..size.x + 1 {
+                        let pos = glam::uvec3(x, y, z);
+                        assert_eq!(octree.get(pos), grid.get(pos));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn uniform() {
+        let mut grid = VoxelGrid::new(glam::uvec3(16, 16, 16)).unwrap();
+
+        let octree = Octree::from_grid(&grid).unwrap();
+        assert_eq!(octree.nodes(), [0; 8]);
+
+        grid.fill_box(glam::UVec3::ZERO, grid.size(), 3).unwrap();
+        let octree = Octree::from_grid(&grid).unwrap();
+        assert_eq!(octree.nodes(), [Octree::LEAF | 3; 8]);
+    }
+
+    #[test]
+    fn memory_use() {
+        let mut grid = VoxelGrid::new(glam::uvec3(128, 128, 128)).unwrap();
+        grid.fill_box(glam::uvec3(0, 0, 0), glam::uvec3(128, 2, 128), 1)
+            .unwrap();
+        grid.fill_box(glam::uvec3(60, 2, 60), glam::uvec3(68, 10, 68), 2)
+            .unwrap();
+        grid.set(glam::uvec3(100, 100, 100), 3).unwrap();
+
+        let octree = Octree::from_grid(&grid).unwrap();
+        let dense_size = (SceneProperties::LEN + grid.voxels().len()) * std::mem::size_of::<u32>();
+
+        assert!(octree.memory_size() * 100 < dense_size);
+        assert_eq!(octree.get(glam::uvec3(100, 100, 100)), Some(3));
+    }
+
+    #[test]
+    fn material_out_of_range() {
+        let mut grid = VoxelGrid::new(glam::uvec3(2, 2, 2)).unwrap();
+        grid.set(glam::uvec3(1, 1, 1), Octree::LEAF).unwrap();
+        assert!(Octree::from_grid(&grid).is_err());
+    }
+
+    #[test]
+    fn upload() {
+        let code = r"
+            #version 460
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer buffer_1 { uint data[]; };
+            layout(binding = 1) buffer buffer_2 { uint result[]; };
+            void main() { result[gl_GlobalInvocationID.x] = data[gl_GlobalInvocationID.x]; }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, code, "test", "main").unwrap();
+
+        let octree = Octree::from_grid(&test_grid(glam::uvec3(5, 6, 7))).unwrap();
+        let len = SceneProperties::LEN + octree.nodes().len();
+
+        let scene_buffer = octree.upload(&instance).unwrap();
+        let result_buffer = CpuBuffer::<u32>::new(&instance, len).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(
+                &program,
+                (len, 1, 1),
+                vec![scene_buffer.bind(0), result_buffer.bind(1)],
+            )
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        let result = result_buffer.read().unwrap();
+        assert_eq!(result[..3], [5, 6, 7]);
+        assert_eq!(result[SceneProperties::LEN..], *octree.nodes());
+        assert_eq!(octree.memory_size(), len * std::mem::size_of::<u32>());
+    }
+}
@@ -2,6 +2,242 @@ use crate::preamble::*;
 
 #[derive(BufferContents, Copy, Clone)]
 #[repr(C)]
-struct SceneProperties {
+pub struct SceneProperties {
     pub size: glam::UVec3,
+    padding_1: u32,
+}
+
+impl SceneProperties {
+    /// Number of `u32` words the header occupies at the start of an uploaded scene buffer.
+    pub const LEN: usize = 4;
+
+    pub fn new(size: glam::UVec3) -> Self {
+        Self { size, padding_1: 0 }
+    }
+
+    pub fn as_words(&self) -> [u32; Self::LEN] {
+        [self.size.x, self.size.y, self.size.z, 0]
+    }
+}
+
+/// Layout of the scene data after the header, which decides the traversal a render shader
+/// needs: `dda.glsl` for a [`VoxelGrid`] and `octree.glsl` for an
+/// [`Octree`](crate::world::Octree).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SceneKind {
+    VoxelGrid,
+    Octree,
+}
+
+/// A scene representation the render shaders read as
+/// `buffer Scene { SceneProperties scene; uint scene_data[]; }`.
+pub trait SceneBuffer {
+    fn kind(&self) -> SceneKind;
+
+    fn properties(&self) -> SceneProperties;
+
+    fn data(&self) -> &[u32];
+
+    /// Uploads the scene header followed by the scene data.
+    fn upload(&self, instance: &Instance) -> Result<GpuBuffer<u32>> {
+        let mut data = Vec::with_capacity(SceneProperties::LEN + self.data().len());
+        data.extend_from_slice(&self.properties().as_words());
+        data.extend_from_slice(self.data());
+
+        let staging_buffer = CpuBuffer::from_vec(instance, data)?;
+        let buffer = GpuBuffer::new(instance, staging_buffer.len())?;
+
+        TaskBuilder::new(instance)?
+            .copy_buffer(&staging_buffer, &buffer)?
+            .build_submit_and_wait()?;
+
+        Ok(buffer)
+    }
+}
+
+/// Dense grid of per-voxel material indices, where material `0` is empty space.
+///
+/// Voxels are stored x-major, so the voxel at `pos` lives at
+/// `pos.z * size.x * size.y + pos.y * size.x + pos.x`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoxelGrid {
+    size: glam::UVec3,
+    voxels: Vec<u32>,
+}
+
+impl VoxelGrid {
+    pub const EMPTY: u32 = 0;
+
+    pub fn new(size: glam::UVec3) -> Result<Self> {
+        if size.cmpeq(glam::UVec3::ZERO).any() {
+            return Err(anyhow!("invalid voxel grid size"));
+        }
+
+        Ok(Self {
+            size,
+            voxels: vec![Self::EMPTY; size.as_u64vec3().element_product() as usize],
+        })
+    }
+
+    pub fn size(&self) -> glam::UVec3 {
+        self.size
+    }
+
+    pub fn voxels(&self) -> &[u32] {
+        &self.voxels
+    }
+
+    pub fn contains(&self, pos: glam::UVec3) -> bool {
+        pos.cmplt(self.size).all()
+    }
+
+    pub fn get(&self, pos: glam::UVec3) -> Option<u32> {
+        self.index(pos).map(|i| self.voxels[i])
+    }
+
+    pub fn set(&mut self, pos: glam::UVec3, material: u32) -> Result<()> {
+        let i = self
+            .index(pos)
+            .ok_or_else(|| anyhow!("voxel position is out of bounds"))?;
+        self.voxels[i] = material;
+        Ok(())
+    }
+
+    /// Sets every voxel in the half-open box `min..max` to `material`.
+    pub fn fill_box(&mut self, min: glam::UVec3, max: glam::UVec3, material: u32) -> Result<()> {
+        if min.cmpge(max).any() {
+            return Err(anyhow!("voxel box is empty"));
+        }
+
+        if max.cmpgt(self.size).any() {
+            return Err(anyhow!("voxel box is out of bounds"));
+        }
+
+        for z in min.z..max.z {
+            for y in min.y..max.y {
+                let row = self.index(glam::uvec3(0, y, z)).unwrap();
+                self.voxels[row + min.x as usize..row + max.x as usize].fill(material);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn index(&self, pos: glam::UVec3) -> Option<usize> {
+        if !self.contains(pos) {
+            return None;
+        }
+
+        let size = self.size.as_u64vec3();
+        let pos = pos.as_u64vec3();
+        Some((pos.z * size.x * size.y + pos.y * size.x + pos.x) as usize)
+    }
+}
+
+impl SceneBuffer for VoxelGrid {
+    fn kind(&self) -> SceneKind {
+        SceneKind::VoxelGrid
+    }
+
+    fn properties(&self) -> SceneProperties {
+        SceneProperties::new(self.size)
+    }
+
+    fn data(&self) -> &[u32] {
+        &self.voxels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creation() {
+        let grid = VoxelGrid::new(glam::uvec3(4, 3, 2)).unwrap();
+        assert_eq!(grid.voxels().len(), 24);
+        assert!(grid.voxels().iter().all(|&v| v == VoxelGrid::EMPTY));
+        assert!(VoxelGrid::new(glam::uvec3(4, 0, 2)).is_err());
+    }
+
+    #[test]
+    fn get_set() {
+        let mut grid = VoxelGrid::new(glam::uvec3(4, 3, 2)).unwrap();
+        grid.set(glam::uvec3(1, 2, 1), 7).unwrap();
+
+        assert_eq!(grid.get(glam::uvec3(1, 2, 1)), Some(7));
+        assert_eq!(grid.get(glam::uvec3(0, 0, 0)), Some(0));
+        assert_eq!(grid.voxels()[4 * 3 + 2 * 4 + 1], 7);
+
+        assert_eq!(grid.get(glam::uvec3(4, 0, 0)), None);
+        assert!(grid.set(glam::uvec3(0, 3, 0), 1).is_err());
+    }
+
+    #[test]
+    fn fill_box() {
+        let mut grid = VoxelGrid::new(glam::uvec3(4, 4, 4)).unwrap();
+        grid.fill_box(glam::uvec3(1, 1, 1), glam::uvec3(3, 4, 2), 5)
+            .unwrap();
+
+        for z in 0..4 {
+            for y in 0..4 {
+                for x in 0..4 {
+                    let inside = (1..3).contains(&x) && (1..4).contains(&y) && z == 1;
+                    let expected = if inside { 5 } else { VoxelGrid::EMPTY };
+                    assert_eq!(grid.get(glam::uvec3(x, y, z)), Some(expected));
+                }
+            }
+        }
+
+        assert!(grid
+            .fill_box(glam::uvec3(0, 0, 0), glam::uvec3(5, 1, 1), 1)
+            .is_err());
+        assert!(grid
+            .fill_box(glam::uvec3(2, 0, 0), glam::uvec3(2, 1, 1), 1)
+            .is_err());
+    }
+
+    #[test]
+    fn upload() {
+        let code = r"
+            #version 460
+            struct Scene { uvec3 size; };
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+            layout(binding = 0) buffer buffer_1 { Scene scene; uint voxels[]; };
+            layout(binding = 1) buffer buffer_2 { Scene scene; uint voxels[]; } result;
+            void main() {
+                uvec3 pos = gl_GlobalInvocationID;
+                uint idx = pos.z * scene.size.x * scene.size.y + pos.y * scene.size.x + pos.x;
+                result.scene = scene;
+                result.voxels[idx] = voxels[idx];
+            }
+        ";
+
+        let instance = Instance::new().unwrap();
+        let program = Program::new(&instance, code, "test", "main").unwrap();
+
+        let mut grid = VoxelGrid::new(glam::uvec3(3, 2, 2)).unwrap();
+        grid.set(glam::uvec3(0, 0, 0), 1).unwrap();
+        grid.set(glam::uvec3(2, 1, 0), 2).unwrap();
+        grid.set(glam::uvec3(1, 0, 1), 3).unwrap();
+
+        let scene_buffer = grid.upload(&instance).unwrap();
+        let result_buffer =
+            CpuBuffer::<u32>::new(&instance, SceneProperties::LEN + grid.voxels().len()).unwrap();
+
+        TaskBuilder::new(&instance)
+            .unwrap()
+            .run_program(
+                &program,
+                (3, 2, 2),
+                vec![scene_buffer.bind(0), result_buffer.bind(1)],
+            )
+            .unwrap()
+            .build_submit_and_wait()
+            .unwrap();
+
+        let result = result_buffer.read().unwrap();
+        assert_eq!(result[..3], [3, 2, 2]);
+        assert_eq!(result[SceneProperties::LEN..], *grid.voxels());
+    }
 }
@@ -0,0 +1,830 @@
+use super::{MaterialProperties, VoxelGrid};
+use crate::preamble::*;
+use std::{collections::HashMap, path::Path};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum VoxError {
+    #[error("failed to read vox file")]
+    Io(#[from] std::io::Error),
+    #[error("missing \"VOX \" file header")]
+    InvalidHeader,
+    #[error("unsupported vox version {0}")]
+    UnsupportedVersion(i32),
+    #[error("unexpected end of file while reading {0}")]
+    Truncated(String),
+    #[error("unsupported chunk \"{0}\"")]
+    UnsupportedChunk(String),
+    #[error("invalid {0} chunk: {1}")]
+    InvalidChunk(String, String),
+    #[error("scene graph references missing node {0}")]
+    MissingNode(i32),
+    #[error("scene graph has a cycle through node {0}")]
+    CyclicSceneGraph(i32),
+    #[error("scene graph references missing model {0}")]
+    MissingModel(i32),
+    #[error("vox file contains no voxels")]
+    NoVoxels,
+    #[error("scene exceeds {MAX_SCENE_EXTENT} voxels along an axis or {MAX_SCENE_VOXELS} in total")]
+    SceneTooLarge,
+}
+
+/// Largest scene extent along any axis, in voxels.
+pub const MAX_SCENE_EXTENT: u32 = 2048;
+
+/// Largest number of voxels in a scene, both placed by the scene graph and in the grid holding
+/// the scene's bounding box.
+pub const MAX_SCENE_VOXELS: u64 = 1 << 28;
+
+/// A voxel scene imported from a MagicaVoxel `.vox` file.
+///
+/// Voxel values are palette indices, so `materials[voxel]` is the material of a voxel and
+/// `materials[0]` is unused. MagicaVoxel is z-up, so models are rotated into the renderer's
+/// y-up space and translated so the scene starts at the origin.
+pub struct VoxScene {
+    pub grid: VoxelGrid,
+    pub materials: Vec<MaterialProperties>,
+}
+
+impl VoxScene {
+    pub fn load(path: impl AsRef<Path>) -> Result<VoxScene, VoxError> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<VoxScene, VoxError> {
+        let mut reader = Reader::new(data, "file header");
+
+        if reader.bytes(4)? != b"VOX " {
+            return Err(VoxError::InvalidHeader);
+        }
+
+        let version = reader.i32()?;
+        if version != 150 && version != 200 {
+            return Err(VoxError::UnsupportedVersion(version));
+        }
+
+        let main = reader.chunk()?;
+        if main.id != *b"MAIN" {
+            return Err(VoxError::InvalidChunk(
+                "MAIN".to_string(),
+                format!("expected MAIN chunk, found \"{}\"", main.name()),
+            ));
+        }
+
+        let mut file = VoxFile::default();
+        let mut children = Reader::new(main.children, "MAIN");
+        while !children.is_empty() {
+            file.read_chunk(children.chunk()?)?;
+        }
+
+        file.into_scene()
+    }
+}
+
+struct Chunk<'a> {
+    id: [u8; 4],
+    content: &'a [u8],
+    children: &'a [u8],
+}
+
+impl Chunk<'_> {
+    fn name(&self) -> String {
+        String::from_utf8_lossy(&self.id).into_owned()
+    }
+
+    fn invalid(&self, reason: &str) -> VoxError {
+        VoxError::InvalidChunk(self.name(), reason.to_string())
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    context: String,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], context: &str) -> Self {
+        Self {
+            data,
+            context: context.to_string(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], VoxError> {
+        if len > self.data.len() {
+            return Err(VoxError::Truncated(self.context.clone()));
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, VoxError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i32(&mut self) -> Result<i32, VoxError> {
+        Ok(i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self) -> Result<usize, VoxError> {
+        usize::try_from(self.i32()?).map_err(|_| VoxError::Truncated(self.context.clone()))
+    }
+
+    fn string(&mut self) -> Result<String, VoxError> {
+        let len = self.len()?;
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+
+    fn dict(&mut self) -> Result<HashMap<String, String>, VoxError> {
+        let len = self.len()?;
+        (0..len)
+            .map(|_| Ok((self.string()?, self.string()?)))
+            .collect()
+    }
+
+    fn chunk(&mut self) -> Result<Chunk<'a>, VoxError> {
+        let id: [u8; 4] = self.bytes(4)?.try_into().unwrap();
+        self.context = format!("{} chunk", String::from_utf8_lossy(&id));
+
+        let content_len = self.len()?;
+        let children_len = self.len()?;
+
+        Ok(Chunk {
+            id,
+            content: self.bytes(content_len)?,
+            children: self.bytes(children_len)?,
+        })
+    }
+}
+
+struct Model {
+    size: glam::IVec3,
+    voxels: Vec<(glam::IVec3, u8)>,
+}
+
+/// Signed permutation matrix, stored as rows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Rotation([glam::IVec3; 3]);
+
+impl Rotation {
+    const IDENTITY: Rotation = Rotation([glam::IVec3::X, glam::IVec3::Y, glam::IVec3::Z]);
+
+    /// Decodes the packed rotation byte used by `nTRN` frames: bits 0-1 and 2-3 hold the column
+    /// of the non-zero entry in the first and second row, and bits 4-6 the sign of each row.
+    fn from_byte(byte: u8) -> Option<Rotation> {
+        let first = (byte & 0b11) as usize;
+        let second = ((byte >> 2) & 0b11) as usize;
+        if first > 2 || second > 2 || first == second {
+            return None;
+        }
+
+        let columns = [first, second, 3 - first - second];
+        let mut rows = [glam::IVec3::ZERO; 3];
+        for (i, row) in rows.iter_mut().enumerate() {
+            row[columns[i]] = if byte & (1 << (4 + i)) != 0 { -1 } else { 1 };
+        }
+
+        Some(Rotation(rows))
+    }
+
+    fn apply(&self, v: glam::IVec3) -> glam::IVec3 {
+        glam::ivec3(self.0[0].dot(v), self.0[1].dot(v), self.0[2].dot(v))
+    }
+
+    /// Like [`Rotation::apply`], but `None` if negating a coordinate overflows.
+    fn checked_apply(&self, v: glam::IVec3) -> Option<glam::IVec3> {
+        let row = |row: glam::IVec3| {
+            let (x, y, z) = (
+                row.x.checked_mul(v.x)?,
+                row.y.checked_mul(v.y)?,
+                row.z.checked_mul(v.z)?,
+            );
+            x.checked_add(y)?.checked_add(z)
+        };
+        Some(glam::ivec3(
+            row(self.0[0])?,
+            row(self.0[1])?,
+            row(self.0[2])?,
+        ))
+    }
+
+    fn then(&self, child: &Rotation) -> Rotation {
+        let columns = [
+            self.apply(child.column(0)),
+            self.apply(child.column(1)),
+            self.apply(child.column(2)),
+        ];
+        Rotation([0, 1, 2].map(|i| glam::ivec3(columns[0][i], columns[1][i], columns[2][i])))
+    }
+
+    fn column(&self, i: usize) -> glam::IVec3 {
+        glam::ivec3(self.0[0][i], self.0[1][i], self.0[2][i])
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Transform {
+    rotation: Rotation,
+    translation: glam::IVec3,
+}
+
+impl Transform {
+    const IDENTITY: Transform = Transform {
+        rotation: Rotation::IDENTITY,
+        translation: glam::IVec3::ZERO,
+    };
+
+    /// `None` if the combined translation overflows.
+    fn then(&self, child: &Transform) -> Option<Transform> {
+        Some(Transform {
+            rotation: self.rotation.then(&child.rotation),
+            translation: checked_add(
+                self.rotation.checked_apply(child.translation)?,
+                self.translation,
+            )?,
+        })
+    }
+
+    fn checked_apply(&self, pos: glam::IVec3) -> Option<glam::IVec3> {
+        checked_add(self.rotation.checked_apply(pos)?, self.translation)
+    }
+}
+
+fn checked_add(a: glam::IVec3, b: glam::IVec3) -> Option<glam::IVec3> {
+    Some(glam::ivec3(
+        a.x.checked_add(b.x)?,
+        a.y.checked_add(b.y)?,
+        a.z.checked_add(b.z)?,
+    ))
+}
+
+enum Node {
+    Transform {
+        transform: Transform,
+        hidden: bool,
+        child: i32,
+    },
+    Group(Vec<i32>),
+    Shape(Vec<i32>),
+}
+
+#[derive(Default)]
+struct VoxFile {
+    models: Vec<Model>,
+    palette: Option<[[u8; 4]; 256]>,
+    nodes: HashMap<i32, Node>,
+}
+
+impl VoxFile {
+    fn read_chunk(&mut self, chunk: Chunk) -> Result<(), VoxError> {
+        let mut reader = Reader::new(chunk.content, &format!("{} chunk", chunk.name()));
+
+        match &chunk.id {
+            b"SIZE" => {
+                let size = glam::ivec3(reader.i32()?, reader.i32()?, reader.i32()?);
+                if size.cmple(glam::IVec3::ZERO).any() || size.cmpgt(glam::IVec3::splat(256)).any()
+                {
+                    return Err(chunk.invalid("model size is out of range"));
+                }
+
+                self.models.push(Model {
+                    size,
+                    voxels: Vec::new(),
+                });
+            }
+            b"XYZI" => {
+                let model = self
+                    .models
+                    .last_mut()
+                    .filter(|model| model.voxels.is_empty())
+                    .ok_or_else(|| chunk.invalid("voxels without a preceding SIZE chunk"))?;
+
+                // check the count before allocating for it, it comes straight from the file
+                let len = reader.len()?;
+                if len
+                    .checked_mul(4)
+                    .is_none_or(|size| size > reader.remaining())
+                {
+                    return Err(VoxError::Truncated(reader.context.clone()));
+                }
+                model.voxels.reserve(len);
+
+                for _ in 0..len {
+                    let [x, y, z, color] = [reader.u8()?, reader.u8()?, reader.u8()?, reader.u8()?];
+                    let pos = glam::ivec3(x as i32, y as i32, z as i32);
+
+                    if pos.cmpge(model.size).any() {
+                        return Err(chunk.invalid("voxel position is out of bounds"));
+                    }
+
+                    model.voxels.push((pos, color));
+                }
+            }
+            b"RGBA" => {
+                let mut palette = [[0; 4]; 256];
+                for color in palette.iter_mut().skip(1) {
+                    *color = reader.bytes(4)?.try_into().unwrap();
+                }
+                reader.bytes(4)?;
+
+                self.palette = Some(palette);
+            }
+            b"nTRN" => {
+                let id = reader.i32()?;
+                let attributes = reader.dict()?;
+                let child = reader.i32()?;
+                let _reserved = reader.i32()?;
+                let _layer = reader.i32()?;
+
+                let frames = reader.len()?;
+                if frames == 0 {
+                    return Err(chunk.invalid("transform has no frames"));
+                }
+
+                let frame = reader.dict()?;
+                for _ in 1..frames {
+                    reader.dict()?;
+                }
+
+                let rotation = match frame.get("_r") {
+                    Some(r) => r
+                        .parse()
+                        .ok()
+                        .and_then(Rotation::from_byte)
+                        .ok_or_else(|| chunk.invalid("invalid rotation"))?,
+                    None => Rotation::IDENTITY,
+                };
+
+                let translation = match frame.get("_t") {
+                    Some(t) => {
+                        let t: Vec<i32> = t
+                            .split_whitespace()
+                            .map(|v| v.parse())
+                            .collect::<Result<_, _>>()
+                            .map_err(|_| chunk.invalid("invalid translation"))?;
+                        <[i32; 3]>::try_from(t)
+                            .map_err(|_| chunk.invalid("invalid translation"))?
+                            .into()
+                    }
+                    None => glam::IVec3::ZERO,
+                };
+
+                let hidden = attributes.get("_hidden").is_some_and(|h| h == "1");
+                let transform = Transform {
+                    rotation,
+                    translation,
+                };
+
+                self.nodes.insert(
+                    id,
+                    Node::Transform {
+                        transform,
+                        hidden,
+                        child,
+                    },
+                );
+            }
+            b"nGRP" => {
+                let id = reader.i32()?;
+                reader.dict()?;
+
+                let len = reader.len()?;
+                let children = (0..len).map(|_| reader.i32()).collect::<Result<_, _>>()?;
+
+                self.nodes.insert(id, Node::Group(children));
+            }
+            b"nSHP" => {
+                let id = reader.i32()?;
+                reader.dict()?;
+
+                let len = reader.len()?;
+                let models = (0..len)
+                    .map(|_| {
+                        let model = reader.i32()?;
+                        reader.dict()?;
+                        Ok(model)
+                    })
+                    .collect::<Result<_, VoxError>>()?;
+
+                self.nodes.insert(id, Node::Shape(models));
+            }
+            b"PACK" | b"MATL" | b"LAYR" | b"rOBJ" | b"rCAM" | b"NOTE" | b"IMAP" => {}
+            _ => return Err(VoxError::UnsupportedChunk(chunk.name())),
+        }
+
+        Ok(())
+    }
+
+    fn into_scene(self) -> Result<VoxScene, VoxError> {
+        let mut voxels = Vec::new();
+
+        if self.nodes.is_empty() {
+            for model in &self.models {
+                voxels.extend_from_slice(&model.voxels);
+            }
+        } else {
+            self.collect_node(0, Transform::IDENTITY, &mut voxels, 0)?;
+        }
+
+        // z-up to y-up, keeping the coordinate system right handed
+        let voxels: Vec<(glam::IVec3, u8)> = voxels
+            .into_iter()
+            .map(|(pos, color)| Some((glam::ivec3(pos.x, pos.z, pos.y.checked_neg()?), color)))
+            .collect::<Option<_>>()
+            .ok_or(VoxError::SceneTooLarge)?;
+
+        let min = voxels
+            .iter()
+            .map(|(pos, _)| *pos)
+            .reduce(glam::IVec3::min)
+            .ok_or(VoxError::NoVoxels)?;
+        let max = voxels
+            .iter()
+            .map(|(pos, _)| *pos)
+            .reduce(glam::IVec3::max)
+            .ok_or(VoxError::NoVoxels)?;
+
+        // the extent is computed in 64 bits, as the corners may be far apart
+        let extent = max.as_i64vec3() - min.as_i64vec3() + 1;
+        if extent
+            .cmpgt(glam::I64Vec3::splat(MAX_SCENE_EXTENT as i64))
+            .any()
+            || extent.as_u64vec3().element_product() > MAX_SCENE_VOXELS
+        {
+            return Err(VoxError::SceneTooLarge);
+        }
+
+        let mut grid = VoxelGrid::new(extent.as_uvec3()).expect("the extent is at least 1");
+        for (pos, color) in voxels {
+            grid.set((pos - min).as_uvec3(), color as u32).unwrap();
+        }
+
+        let palette = self.palette.unwrap_or_else(default_palette);
+        let materials = palette
+            .iter()
+            .map(|&[r, g, b, _]| {
+                let color = glam::vec3(r as f32, g as f32, b as f32) / 255.0;
+                MaterialProperties::new(srgb_to_linear(color), glam::Vec4::ZERO)
+            })
+            .collect();
+
+        Ok(VoxScene { grid, materials })
+    }
+
+    fn collect_node(
+        &self,
+        id: i32,
+        transform: Transform,
+        voxels: &mut Vec<(glam::IVec3, u8)>,
+        depth: usize,
+    ) -> Result<(), VoxError> {
+        // a well formed scene graph is a tree, so anything deeper than the node count is a cycle
+        if depth > self.nodes.len() {
+            return Err(VoxError::CyclicSceneGraph(id));
+        }
+
+        match self.nodes.get(&id).ok_or(VoxError::MissingNode(id))? {
+            Node::Transform {
+                transform: local,
+                hidden,
+                child,
+            } => {
+                if !hidden {
+                    let transform = transform.then(local).ok_or(VoxError::SceneTooLarge)?;
+                    self.collect_node(*child, transform, voxels, depth + 1)?;
+                }
+            }
+            Node::Group(children) => {
+                for child in children {
+                    self.collect_node(*child, transform, voxels, depth + 1)?;
+                }
+            }
+            Node::Shape(models) => {
+                for &id in models {
+                    let model = usize::try_from(id)
+                        .ok()
+                        .and_then(|i| self.models.get(i))
+                        .ok_or(VoxError::MissingModel(id))?;
+
+                    // check the count before allocating, models can be placed any number of times
+                    if (voxels.len() + model.voxels.len()) as u64 > MAX_SCENE_VOXELS {
+                        return Err(VoxError::SceneTooLarge);
+                    }
+
+                    // models are positioned around their center voxel
+                    let pivot = model.size / 2;
+                    for &(pos, color) in &model.voxels {
+                        let pos = transform
+                            .checked_apply(pos - pivot)
+                            .ok_or(VoxError::SceneTooLarge)?;
+                        voxels.push((pos, color));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn srgb_to_linear(color: glam::Vec3) -> glam::Vec3 {
+    let f = |c: f32| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    glam::vec3(f(color.x), f(color.y), f(color.z))
+}
+
+/// MagicaVoxel's default palette, used when a file has no `RGBA` chunk: a 6x6x6 colour cube
+/// without black, followed by red, green, blue and grey ramps.
+fn default_palette() -> [[u8; 4]; 256] {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+
+    let mut colors = Vec::with_capacity(256);
+    colors.push([0, 0, 0, 0]);
+
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                colors.push([r, g, b, 0xff]);
+            }
+        }
+    }
+    colors.pop();
+
+    for channel in [0, 1, 2] {
+        for value in RAMP {
+            let mut color = [0, 0, 0, 0xff];
+            color[channel] = value;
+            colors.push(color);
+        }
+    }
+
+    for value in RAMP {
+        colors.push([value, value, value, 0xff]);
+    }
+
+    colors.try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut data = id.to_vec();
+        data.extend((content.len() as i32).to_le_bytes());
+        data.extend((children.len() as i32).to_le_bytes());
+        data.extend(content);
+        data.extend(children);
+        data
+    }
+
+    fn vox_file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = b"VOX ".to_vec();
+        data.extend(200i32.to_le_bytes());
+        data.extend(chunk(b"MAIN", &[], &chunks.concat()));
+        data
+    }
+
+    fn ints(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn dict(pairs: &[(&str, &str)]) -> Vec<u8> {
+        let mut data = ints(&[pairs.len() as i32]);
+        for (key, value) in pairs {
+            for s in [key, value] {
+                data.extend(ints(&[s.len() as i32]));
+                data.extend(s.as_bytes());
+            }
+        }
+        data
+    }
+
+    fn model(size: [i32; 3], voxels: &[[u8; 4]]) -> Vec<Vec<u8>> {
+        let mut xyzi = ints(&[voxels.len() as i32]);
+        xyzi.extend(voxels.concat());
+        vec![
+            chunk(b"SIZE", &ints(&size), &[]),
+            chunk(b"XYZI", &xyzi, &[]),
+        ]
+    }
+
+    fn transform(id: i32, child: i32, frame: &[(&str, &str)]) -> Vec<u8> {
+        let mut content = ints(&[id]);
+        content.extend(dict(&[]));
+        content.extend(ints(&[child, -1, 0, 1]));
+        content.extend(dict(frame));
+        chunk(b"nTRN", &content, &[])
+    }
+
+    fn group(id: i32, children: &[i32]) -> Vec<u8> {
+        let mut content = ints(&[id]);
+        content.extend(dict(&[]));
+        content.extend(ints(&[children.len() as i32]));
+        content.extend(ints(children));
+        chunk(b"nGRP", &content, &[])
+    }
+
+    fn shape(id: i32, model: i32) -> Vec<u8> {
+        let mut content = ints(&[id]);
+        content.extend(dict(&[]));
+        content.extend(ints(&[1, model]));
+        content.extend(dict(&[]));
+        chunk(b"nSHP", &content, &[])
+    }
+
+    fn palette(colors: &[[u8; 4]]) -> Vec<u8> {
+        let mut content = vec![0; 256 * 4];
+        content[..colors.len() * 4].copy_from_slice(&colors.concat());
+        chunk(b"RGBA", &content, &[])
+    }
+
+    #[test]
+    fn single_model() {
+        let mut chunks = model([2, 3, 4], &[[0, 0, 0, 1], [1, 2, 3, 2]]);
+        chunks.push(palette(&[[255, 0, 0, 255], [0, 255, 0, 255]]));
+
+        let scene = VoxScene::parse(&vox_file(&chunks)).unwrap();
+
+        assert_eq!(scene.grid.size(), glam::uvec3(2, 4, 3));
+        assert_eq!(scene.grid.get(glam::uvec3(0, 0, 2)), Some(1));
+        assert_eq!(scene.grid.get(glam::uvec3(1, 3, 0)), Some(2));
+        assert_eq!(scene.grid.voxels().iter().filter(|&&v| v != 0).count(), 2);
+
+        assert_eq!(scene.materials.len(), 256);
+        assert_eq!(scene.materials[1].color, glam::vec3(1.0, 0.0, 0.0));
+        assert_eq!(scene.materials[2].color, glam::vec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn scene_graph() {
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.extend(model([1, 1, 1], &[[0, 0, 0, 2]]));
+        chunks.push(transform(0, 1, &[]));
+        chunks.push(group(1, &[2, 4]));
+        chunks.push(transform(2, 3, &[("_t", "0 0 0")]));
+        chunks.push(shape(3, 0));
+        chunks.push(transform(4, 5, &[("_t", "3 0 0")]));
+        chunks.push(shape(5, 1));
+
+        let scene = VoxScene::parse(&vox_file(&chunks)).unwrap();
+
+        assert_eq!(scene.grid.size(), glam::uvec3(4, 1, 1));
+        assert_eq!(scene.grid.get(glam::uvec3(0, 0, 0)), Some(1));
+        assert_eq!(scene.grid.get(glam::uvec3(3, 0, 0)), Some(2));
+    }
+
+    #[test]
+    fn scene_too_large() {
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.extend(model([1, 1, 1], &[[0, 0, 0, 2]]));
+        chunks.push(transform(0, 1, &[]));
+        chunks.push(group(1, &[2, 4]));
+        chunks.push(transform(2, 3, &[]));
+        chunks.push(shape(3, 0));
+        chunks.push(transform(4, 5, &[("_t", "100000 100000 100000")]));
+        chunks.push(shape(5, 1));
+        assert!(matches!(
+            VoxScene::parse(&vox_file(&chunks)),
+            Err(VoxError::SceneTooLarge)
+        ));
+
+        // nested translations that overflow i32
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.push(transform(0, 1, &[("_t", "2147483647 0 0")]));
+        chunks.push(transform(1, 2, &[("_t", "1 0 0")]));
+        chunks.push(shape(2, 0));
+        assert!(matches!(
+            VoxScene::parse(&vox_file(&chunks)),
+            Err(VoxError::SceneTooLarge)
+        ));
+
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.push(transform(0, 1, &[("_t", "0 -2147483648 0")]));
+        chunks.push(shape(1, 0));
+        assert!(matches!(
+            VoxScene::parse(&vox_file(&chunks)),
+            Err(VoxError::SceneTooLarge)
+        ));
+    }
+
+    #[test]
+    fn cyclic_scene_graph() {
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.push(transform(0, 1, &[]));
+        chunks.push(group(1, &[0]));
+        assert!(matches!(
+            VoxScene::parse(&vox_file(&chunks)),
+            Err(VoxError::CyclicSceneGraph(_))
+        ));
+
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.push(transform(0, 7, &[]));
+        assert!(matches!(
+            VoxScene::parse(&vox_file(&chunks)),
+            Err(VoxError::MissingNode(7))
+        ));
+    }
+
+    #[test]
+    fn rotation() {
+        assert_eq!(Rotation::from_byte(4), Some(Rotation::IDENTITY));
+        assert_eq!(Rotation::from_byte(0), None);
+
+        // maps +x to +y and +y to -x
+        let mut chunks = model([3, 1, 1], &[[0, 0, 0, 1], [2, 0, 0, 2]]);
+        chunks.push(transform(0, 1, &[("_r", "17")]));
+        chunks.push(shape(1, 0));
+
+        let scene = VoxScene::parse(&vox_file(&chunks)).unwrap();
+
+        assert_eq!(scene.grid.size(), glam::uvec3(1, 1, 3));
+        assert_eq!(scene.grid.get(glam::uvec3(0, 0, 2)), Some(1));
+        assert_eq!(scene.grid.get(glam::uvec3(0, 0, 0)), Some(2));
+    }
+
+    #[test]
+    fn default_palette() {
+        let scene = VoxScene::parse(&vox_file(&model([1, 1, 1], &[[0, 0, 0, 1]]))).unwrap();
+
+        assert_eq!(scene.materials[1].color, glam::vec3(1.0, 1.0, 1.0));
+        assert_eq!(
+            scene.materials[216].color,
+            srgb_to_linear(glam::vec3(238.0 / 255.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            scene.materials[226].color,
+            srgb_to_linear(glam::vec3(0.0, 238.0 / 255.0, 0.0))
+        );
+        assert_eq!(
+            scene.materials[236].color,
+            srgb_to_linear(glam::vec3(0.0, 0.0, 238.0 / 255.0))
+        );
+        assert_eq!(
+            scene.materials[255].color,
+            srgb_to_linear(glam::Vec3::splat(17.0 / 255.0))
+        );
+    }
+
+    #[test]
+    fn invalid_header() {
+        let mut data = vox_file(&model([1, 1, 1], &[[0, 0, 0, 1]]));
+        data[0] = b'B';
+        assert!(matches!(
+            VoxScene::parse(&data),
+            Err(VoxError::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let data = vox_file(&model([1, 1, 1], &[[0, 0, 0, 1]]));
+        assert!(matches!(
+            VoxScene::parse(&data[..data.len() - 2]),
+            Err(VoxError::Truncated(_))
+        ));
+
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks[1] = chunk(b"XYZI", &ints(&[i32::MAX, 0]), &[]);
+        assert!(matches!(
+            VoxScene::parse(&vox_file(&chunks)),
+            Err(VoxError::Truncated(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_chunk() {
+        let mut chunks = model([1, 1, 1], &[[0, 0, 0, 1]]);
+        chunks.push(chunk(b"ABCD", &[0; 4], &[]));
+
+        match VoxScene::parse(&vox_file(&chunks)) {
+            Err(VoxError::UnsupportedChunk(id)) => assert_eq!(id, "ABCD"),
+            _ => panic!("expected unsupported chunk error"),
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_voxel() {
+        let data = vox_file(&model([1, 1, 1], &[[1, 0, 0, 1]]));
+        assert!(matches!(
+            VoxScene::parse(&data),
+            Err(VoxError::InvalidChunk(..))
+        ));
+    }
+}